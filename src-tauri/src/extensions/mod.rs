@@ -1,5 +1,6 @@
 pub mod types;
 
+use crate::instances::{find_instance, modify_instance, InstanceError};
 use crate::persist::PersistedData;
 use crate::state::{Extension, Mod};
 use std::path::PathBuf;
@@ -8,22 +9,22 @@ use tauri::State;
 #[tauri::command]
 pub async fn set_extension_state(
     updated: Vec<Extension>,
+    instance: Option<String>,
     persisted_data: State<'_, PersistedData>,
-) -> Result<(), ()> {
+) -> Result<(), InstanceError> {
     println!("set extension state, {:?}", updated);
-    persisted_data.put_value("extensions", updated);
+    modify_instance(&persisted_data, instance.as_deref(), |it| it.extensions = updated)?;
 
     Ok(())
 }
 
 #[tauri::command]
 pub async fn get_extension_state(
+    instance: Option<String>,
     persisted_data: State<'_, PersistedData>,
-) -> Result<Vec<Extension>, ()> {
+) -> Result<Vec<Extension>, InstanceError> {
     println!("Getting extensions");
-    Ok(persisted_data
-        .read_value("extensions")
-        .unwrap_or(Vec::new()))
+    Ok(find_instance(&persisted_data, instance.as_deref())?.extensions)
 }
 
 #[tauri::command]
@@ -0,0 +1,320 @@
+use crate::launch::hooks::split_command;
+use crate::launch::jvm::{check_args, check_memory};
+use crate::persist::PersistedData;
+use crate::settings::JvmPreset;
+use crate::state::{Extension, Mod, ProcessRegistry};
+use crate::{minecraft_dir, yakclient_dir};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::{create_dir_all, remove_dir_all};
+use std::io;
+use std::path::{Path, PathBuf};
+use tauri::State;
+use uuid::Uuid;
+
+const INSTANCES_KEY: &str = "instances";
+
+// What the instance made out of the old global extensions and mods starts out on
+const DEFAULT_INSTANCE_ID: &str = "default";
+const DEFAULT_VERSION: &str = "1.21.4";
+
+// A game directory with its own version, extensions and mods. Libraries, assets and runtimes are
+// shared between every instance.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Instance {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub game_dir: PathBuf,
+    #[serde(default)]
+    pub extensions: Vec<Extension>,
+    #[serde(default)]
+    pub mods: Vec<Mod>,
+    #[serde(default)]
+    pub jvm: InstanceJvmSettings,
+    #[serde(default)]
+    pub launch: InstanceLaunchSettings,
+    pub icon: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct InstanceJvmSettings {
+    // Added after the version's own JVM arguments and the global ones
+    #[serde(default)]
+    pub args: Vec<String>,
+    // Each of these replaces the global setting when it's set
+    #[serde(default)]
+    pub min_memory: Option<u32>,
+    #[serde(default)]
+    pub max_memory: Option<u32>,
+    #[serde(default)]
+    pub preset: Option<JvmPreset>,
+    // Added after the global game arguments
+    #[serde(default)]
+    pub game_args: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct InstanceLaunchSettings {
+    // Put in front of the java command, like `nice -n 10` or `mangohud`
+    pub wrapper: Option<String>,
+    // Shell commands run in the game directory before the game starts and after it exits. A failing
+    // pre-launch hook stops the launch.
+    pub pre_launch: Option<String>,
+    pub post_exit: Option<String>,
+    // Passed to the game and both hooks
+    pub env: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+pub enum InstanceError {
+    NotFound(String),
+    Invalid(String),
+    Running(String),
+    IoError(io::Error),
+}
+
+impl Display for InstanceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            InstanceError::NotFound(id) => format!("No instance with the id '{}'", id),
+            InstanceError::Invalid(s) => s.clone(),
+            InstanceError::Running(id) => format!("The instance '{}' is running, stop it first", id),
+            InstanceError::IoError(e) => e.to_string(),
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl Serialize for InstanceError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+impl Instance {
+    pub fn validate(&self) -> Result<(), InstanceError> {
+        if self.name.trim().is_empty() {
+            return Err(InstanceError::Invalid("An instance needs a name".to_string()));
+        }
+        if self.version.trim().is_empty() {
+            return Err(InstanceError::Invalid("An instance needs a Minecraft version".to_string()));
+        }
+
+        // The rest depends on the global settings, which are only known at launch
+        check_memory(self.jvm.min_memory, self.jvm.max_memory).map_err(InstanceError::Invalid)?;
+        check_args(&self.jvm.args, &self.jvm.game_args).map_err(InstanceError::Invalid)?;
+
+        if let Some(wrapper) = &self.launch.wrapper {
+            split_command(wrapper).map_err(InstanceError::Invalid)?;
+        }
+        if let Some(key) = self.launch.env.keys().find(|it| it.is_empty() || it.contains(['=', '\0'])) {
+            return Err(InstanceError::Invalid(format!("'{}' isn't a valid environment variable name", key)));
+        }
+
+        Ok(())
+    }
+}
+
+// Where instances keep their game directory unless told otherwise
+pub fn instances_dir() -> PathBuf {
+    yakclient_dir().join("instances")
+}
+
+pub fn read_instances(persisted_data: &PersistedData) -> Vec<Instance> {
+    if let Some(instances) = persisted_data.read_value(INSTANCES_KEY) {
+        return instances;
+    }
+
+    // Before instances everything was global and the game ran straight out of .minecraft
+    let instances = vec![Instance {
+        id: DEFAULT_INSTANCE_ID.to_string(),
+        name: "Default".to_string(),
+        version: DEFAULT_VERSION.to_string(),
+        game_dir: minecraft_dir(),
+        extensions: persisted_data.read_value("extensions").unwrap_or(Vec::new()),
+        mods: persisted_data.read_value("mods").unwrap_or(Vec::new()),
+        jvm: InstanceJvmSettings::default(),
+        launch: InstanceLaunchSettings::default(),
+        icon: None,
+    }];
+
+    persisted_data.put_value(INSTANCES_KEY, &instances);
+    persisted_data.remove_value("extensions");
+    persisted_data.remove_value("mods");
+
+    instances
+}
+
+// Without an id the first instance is used, which is what the single instance pages work with
+pub fn find_instance(
+    persisted_data: &PersistedData,
+    id: Option<&str>,
+) -> Result<Instance, InstanceError> {
+    let instances = read_instances(persisted_data);
+
+    match id {
+        Some(id) => instances.into_iter().find(|it| it.id == id),
+        None => instances.into_iter().next(),
+    }.ok_or_else(|| InstanceError::NotFound(id.unwrap_or_default().to_string()))
+}
+
+// Every change to the list goes through here, so two commands changing instances at once can't
+// write over each other
+fn update_instances<R>(
+    persisted_data: &PersistedData,
+    update: impl FnOnce(&mut Vec<Instance>) -> Result<R, InstanceError>,
+) -> Result<R, InstanceError> {
+    // Migrates first, it touches more than just the list
+    read_instances(persisted_data);
+
+    persisted_data.update_value(INSTANCES_KEY, |instances: &mut Option<Vec<Instance>>| {
+        update(instances.get_or_insert_with(Vec::new))
+    })
+}
+
+pub fn modify_instance(
+    persisted_data: &PersistedData,
+    id: Option<&str>,
+    modify: impl FnOnce(&mut Instance),
+) -> Result<Instance, InstanceError> {
+    update_instances(persisted_data, |instances| {
+        let instance = match id {
+            Some(id) => instances.iter_mut().find(|it| it.id == id),
+            None => instances.first_mut(),
+        }.ok_or_else(|| InstanceError::NotFound(id.unwrap_or_default().to_string()))?;
+
+        let id = instance.id.clone();
+        modify(instance);
+        // The id is what everything else refers to the instance by
+        instance.id = id;
+        instance.validate()?;
+
+        Ok(instance.clone())
+    })
+}
+
+#[tauri::command]
+pub fn list_instances(persisted_data: State<'_, PersistedData>) -> Vec<Instance> {
+    read_instances(&persisted_data)
+}
+
+#[tauri::command]
+pub fn get_instance(
+    id: String,
+    persisted_data: State<'_, PersistedData>,
+) -> Result<Instance, InstanceError> {
+    find_instance(&persisted_data, Some(&id))
+}
+
+#[tauri::command]
+pub fn create_instance(
+    name: String,
+    version: String,
+    icon: Option<String>,
+    game_dir: Option<String>,
+    persisted_data: State<'_, PersistedData>,
+) -> Result<Instance, InstanceError> {
+    let id = Uuid::new_v4().to_string();
+
+    let instance = Instance {
+        game_dir: game_dir
+            .map(PathBuf::from)
+            .unwrap_or_else(|| instances_dir().join(&id)),
+        id,
+        name,
+        version,
+        extensions: vec![],
+        mods: vec![],
+        jvm: InstanceJvmSettings::default(),
+        launch: InstanceLaunchSettings::default(),
+        icon,
+    };
+    instance.validate()?;
+
+    create_dir_all(&instance.game_dir).map_err(InstanceError::IoError)?;
+
+    update_instances(&persisted_data, |instances| {
+        instances.push(instance.clone());
+        Ok(())
+    })?;
+
+    Ok(instance)
+}
+
+#[tauri::command]
+pub fn update_instance(
+    instance: Instance,
+    persisted_data: State<'_, PersistedData>,
+) -> Result<Instance, InstanceError> {
+    let instance = modify_instance(&persisted_data, Some(&instance.id.clone()), |it| *it = instance)?;
+    // Only once it's known to exist and be valid, so a bad update leaves nothing behind
+    create_dir_all(&instance.game_dir).map_err(InstanceError::IoError)?;
+
+    Ok(instance)
+}
+
+// Game directories are only ever deleted if the launcher created them
+#[tauri::command]
+pub async fn delete_instance(
+    id: String,
+    delete_files: bool,
+    persisted_data: State<'_, PersistedData>,
+    processes: State<'_, ProcessRegistry>,
+) -> Result<(), InstanceError> {
+    // Its game directory is still in use
+    if processes.is_instance_running(&id).await {
+        return Err(InstanceError::Running(id));
+    }
+
+    let instance = find_instance(&persisted_data, Some(&id))?;
+
+    // Deleted outside of the list's lock, it can take a while
+    if delete_files && is_managed_dir(&instance.game_dir) && instance.game_dir.exists() {
+        remove_dir_all(&instance.game_dir).map_err(InstanceError::IoError)?;
+    }
+
+    update_instances(&persisted_data, |instances| {
+        instances.retain(|it| it.id != id);
+        Ok(())
+    })
+}
+
+fn is_managed_dir(path: &Path) -> bool {
+    path.starts_with(instances_dir()) && path != instances_dir()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_and_modify_instances() {
+        let data = PersistedData::new_empty();
+        data.put_value("mods", vec![Mod {
+            project_id: "P7dR8mSH".to_string(),
+            loader: "fabric".to_string(),
+        }]);
+
+        let instances = read_instances(&data);
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].id, DEFAULT_INSTANCE_ID);
+        assert_eq!(instances[0].mods.len(), 1);
+        assert!(data.read_value::<Vec<Mod>, _>("mods").is_none());
+
+        let updated = modify_instance(&data, None, |it| {
+            it.id = "changed".to_string();
+            it.version = "1.8.9".to_string();
+        }).unwrap();
+        assert_eq!(updated.id, DEFAULT_INSTANCE_ID);
+        assert_eq!(find_instance(&data, Some(DEFAULT_INSTANCE_ID)).unwrap().version, "1.8.9");
+
+        assert!(modify_instance(&data, None, |it| it.name = " ".to_string()).is_err());
+        assert!(find_instance(&data, Some("missing")).is_err());
+    }
+}
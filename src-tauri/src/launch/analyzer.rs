@@ -0,0 +1,152 @@
+use crate::state::Extension;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// Known problems, matched against the game's output and crash reports
+const CRASH_RULES: &str = include_str!("../../crash-rules.json");
+
+// Parts of mixin config names that say nothing about whose they are
+const CONFIG_WORDS: &[&str] = &["mixins", "mixin", "client", "common", "server", "refmap"];
+
+// `diagnosis` and `fix` can refer to the pattern's named groups with `$name`. Rules with a `config`
+// group can also use `$extension`, the extension that mixin config most likely comes from.
+#[derive(Deserialize, Debug)]
+pub struct CrashRule {
+    pub id: String,
+    pub pattern: String,
+    pub title: String,
+    pub diagnosis: String,
+    pub fix: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Diagnosis {
+    pub id: String,
+    pub title: String,
+    pub diagnosis: String,
+    pub fix: String,
+    // What the rule matched
+    pub evidence: String,
+}
+
+pub fn crash_rules() -> Vec<CrashRule> {
+    serde_json::from_str(CRASH_RULES).expect("The embedded crash rules are invalid")
+}
+
+// Every rule that matches any of the texts, once, in the order the rules are listed
+pub fn analyze(texts: &[&str], extensions: &[Extension]) -> Vec<Diagnosis> {
+    crash_rules()
+        .into_iter()
+        .filter_map(|rule| {
+            let regex = Regex::new(&rule.pattern).ok()?;
+            let captures = texts.iter().find_map(|text| regex.captures(text))?;
+
+            let extension = captures.name("config").map(|config| {
+                match mixin_config_owner(config.as_str(), extensions) {
+                    Some(extension) => extension.descriptor.clone(),
+                    None => format!("the extension or mod that ships {}", config.as_str()),
+                }
+            });
+
+            let expand = |template: &str| {
+                let template = match &extension {
+                    Some(extension) => template.replace("$extension", &extension.replace('$', "$$")),
+                    None => template.to_string(),
+                };
+
+                let mut expanded = String::new();
+                captures.expand(&template, &mut expanded);
+                expanded
+            };
+
+            Some(Diagnosis {
+                diagnosis: expand(&rule.diagnosis),
+                fix: expand(&rule.fix),
+                evidence: captures[0].trim().to_string(),
+                id: rule.id,
+                title: rule.title,
+            })
+        })
+        .collect()
+}
+
+// Mixin configs are usually named after the project, like `minimap.mixins.json`, so the extension is
+// the one whose artifact or group name shares a word with it
+pub fn mixin_config_owner<'a>(config: &str, extensions: &'a [Extension]) -> Option<&'a Extension> {
+    let words = |text: &str| {
+        text.split(['.', '-', '_', ':'])
+            .map(str::to_lowercase)
+            .filter(|it| !it.is_empty())
+            .collect::<Vec<_>>()
+    };
+
+    let config_words = words(config.trim_end_matches(".json"))
+        .into_iter()
+        .filter(|it| !CONFIG_WORDS.contains(&it.as_str()))
+        .collect::<Vec<_>>();
+
+    extensions.iter().find(|extension| {
+        let mut parts = extension.descriptor.split(':');
+        let group = parts.next().unwrap_or_default();
+        let artifact = parts.next().unwrap_or_default();
+
+        // Only the last part of the group, the rest is a domain every project of an author shares
+        let mut names = words(artifact);
+        names.extend(group.rsplit('.').next().map(str::to_lowercase));
+
+        config_words.iter().any(|it| names.contains(it))
+    })
+}
+
+// Appended to exported logs
+pub fn format_diagnoses(diagnoses: &[Diagnosis]) -> String {
+    let mut text = String::from("\n---- Diagnoses ----\n");
+
+    for diagnosis in diagnoses {
+        text.push_str(&format!(
+            "\n{}\n  {}\n  Fix: {}\n  Matched: {}\n",
+            diagnosis.title, diagnosis.diagnosis, diagnosis.fix, diagnosis.evidence
+        ));
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::RepositoryType;
+
+    #[test]
+    fn test_crash_rules() {
+        for rule in crash_rules() {
+            assert!(Regex::new(&rule.pattern).is_ok(), "{} has an invalid pattern", rule.id);
+        }
+
+        let output = "[12:01:44] [main/ERROR]: Mixin [MinimapMixin] from phase [DEFAULT] in config [minimap.mixins.json] FAILED during APPLY
+Exception in thread \"main\" java.lang.UnsupportedClassVersionError: dev/example/Main has been compiled by a more recent version of the Java Runtime (class file version 65.0), this version of the Java Runtime only recognizes class file versions up to 61.0";
+        let fatal_error = "# Problematic frame:
+# C  [atio6axx.dll+0x1a2b3c]";
+
+        let extensions = vec![Extension {
+            descriptor: "dev.example.minimap:minimap-ext:1.0".to_string(),
+            repository: "https://repo.example.dev".to_string(),
+            repository_type: RepositoryType::REMOTE,
+        }];
+
+        let diagnoses = analyze(&[output, fatal_error], &extensions);
+        let ids = diagnoses.iter().map(|it| it.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["unsupported_class_version", "graphics_driver_crash", "mixin_apply_failed"]);
+
+        assert!(diagnoses[0].diagnosis.starts_with("dev/example/Main was compiled"));
+        assert!(diagnoses[0].diagnosis.contains("class file version 65.0"));
+        assert_eq!(diagnoses[1].diagnosis, "The JVM crashed inside the graphics driver (atio6axx.dll).");
+        assert!(diagnoses[2].diagnosis.contains("It comes from dev.example.minimap:minimap-ext:1.0,"));
+        assert_eq!(diagnoses[2].fix, "Update or disable dev.example.minimap:minimap-ext:1.0.");
+
+        let unknown = analyze(&[output], &[]);
+        assert_eq!(unknown[1].fix, "Update or disable the extension or mod that ships minimap.mixins.json.");
+
+        assert!(analyze(&["[main/INFO]: Setting user: Player"], &extensions).is_empty());
+    }
+}
@@ -0,0 +1,427 @@
+use crate::instances::Instance;
+use crate::launch::java::installed_java_executable;
+use crate::launch::logs::{list_session_logs_in, read_session_log_in};
+use crate::launch::minecraft::MinecraftEnvironment;
+use crate::launch::redact::Redactor;
+use crate::mods::stored_mod_extension;
+use crate::state::{Extension, RepositoryType};
+use crate::task::download::DownloadScheduler;
+use crate::util::rand::generate_random_id;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{read, read_dir, remove_file, rename, File};
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+// Crash reports pile up, only the most recent ones are of any use
+const MAX_CRASH_REPORTS: usize = 5;
+
+// Where everything in a diagnostic bundle comes from
+pub struct BundleSources<'a> {
+    pub instance: &'a Instance,
+    pub minecraft_dir: &'a Path,
+    pub runtime_dir: &'a Path,
+    pub mods_repo: &'a Path,
+    pub session_logs_dir: &'a Path,
+    // The launcher's persisted data, as written to `config.json`
+    pub config: HashMap<String, Value>,
+    pub client_version: &'a str,
+    // How many of the instance's most recent session logs to include
+    pub sessions: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SystemInfo {
+    pub os: String,
+    pub os_version: String,
+    pub arch: String,
+    pub launcher_version: String,
+    pub client_version: String,
+    pub instance_id: String,
+    pub minecraft_version: String,
+}
+
+// Entries are collected before anything is written, so a bundle is never left half done
+struct Bundle<'a> {
+    entries: Vec<(String, String)>,
+    // Whatever couldn't be collected, written to `problems.txt`
+    problems: Vec<String>,
+    redactor: &'a Redactor,
+}
+
+impl<'a> Bundle<'a> {
+    fn add(&mut self, name: impl Into<String>, contents: &str) {
+        self.entries.push((name.into(), self.redactor.redact(contents)));
+    }
+
+    fn add_json(&mut self, name: &str, value: &impl Serialize) {
+        match serde_json::to_string_pretty(value) {
+            Ok(json) => self.add(name, &json),
+            Err(e) => self.problem(format!("Failed to write {}: {}", name, e)),
+        }
+    }
+
+    fn problem(&mut self, problem: String) {
+        self.problems.push(problem);
+    }
+}
+
+// Writes a zip with everything needed to look into a problem with an instance, and returns what
+// couldn't be included. Everything in it is redacted.
+pub async fn write_diagnostic_bundle(
+    path: &Path,
+    sources: BundleSources<'_>,
+    redactor: &Redactor,
+    downloads: &DownloadScheduler,
+) -> io::Result<Vec<String>> {
+    let instance = sources.instance;
+    let mut bundle = Bundle {
+        entries: Vec::new(),
+        problems: Vec::new(),
+        redactor,
+    };
+
+    let info = os_info::get();
+    bundle.add_json("system.json", &SystemInfo {
+        os: info.os_type().to_string(),
+        os_version: info.version().to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        launcher_version: env!("CARGO_PKG_VERSION").to_string(),
+        client_version: sources.client_version.to_string(),
+        instance_id: instance.id.clone(),
+        minecraft_version: instance.version.clone(),
+    });
+
+    // The login is left out altogether, the rest is only as personal as what redaction misses
+    let mut config = sources.config.clone();
+    if config.contains_key("ms_auth") {
+        config.insert("ms_auth".to_string(), Value::String("<redacted>".to_string()));
+    }
+    // Environment variables are where API keys and tokens end up, only their names are kept
+    if let Some(instances) = config.get_mut("instances").and_then(Value::as_array_mut) {
+        for env in instances.iter_mut().filter_map(|it| it.pointer_mut("/launch/env")) {
+            if let Some(env) = env.as_object_mut() {
+                env.values_mut().for_each(|it| *it = Value::String("<redacted>".to_string()));
+            }
+        }
+    }
+    bundle.add_json("settings.json", &config.get("settings"));
+    bundle.add_json("config.json", &config);
+
+    let mut redacted_instance = instance.clone();
+    redacted_instance.launch.env.values_mut().for_each(|it| *it = "<redacted>".to_string());
+    bundle.add_json("instance.json", &redacted_instance);
+
+    add_version(&mut bundle, &sources).await;
+    add_extensions(&mut bundle, &sources, downloads).await;
+    add_logs(&mut bundle, &sources);
+
+    if !bundle.problems.is_empty() {
+        let problems = bundle.problems.join("\n");
+        bundle.add("problems.txt", &problems);
+    }
+
+    // Written aside and moved into place once complete, so a failed write never leaves a broken
+    // zip where the user asked for one
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let staging = path.with_file_name(format!("{}.tmp-{}", file_name, generate_random_id(6)));
+
+    let result = write_zip(&staging, &bundle.entries).and_then(|_| rename(&staging, path));
+    if result.is_err() {
+        let _ = remove_file(&staging);
+    }
+    result?;
+
+    Ok(bundle.problems)
+}
+
+fn write_zip(path: &Path, entries: &[(String, String)]) -> io::Result<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    for (name, contents) in entries {
+        zip.start_file(name.as_str(), SimpleFileOptions::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        zip.write_all(contents.as_bytes())?;
+    }
+    zip.finish().map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    Ok(())
+}
+
+// The version as it's launched, with its parents merged in and libraries patched, along with the
+// runtime it needs
+async fn add_version(bundle: &mut Bundle<'_>, sources: &BundleSources<'_>) {
+    let version = &sources.instance.version;
+
+    let merged = MinecraftEnvironment::installed_version_chain(sources.minecraft_dir, version)
+        .and_then(MinecraftEnvironment::merge_version_chain);
+    let merged = match merged {
+        Ok(merged) => merged,
+        Err(e) => {
+            bundle.problem(format!("Failed to read version {}: {}", version, e));
+            return;
+        }
+    };
+    bundle.add_json("version.json", &merged);
+
+    let info = serde_json::to_vec(&merged)
+        .map_err(|e| e.to_string())
+        .and_then(|it| MinecraftEnvironment::read_version_info(&it).map_err(|e| e.to_string()));
+    let info = match info {
+        Ok(info) => info,
+        Err(e) => {
+            bundle.problem(format!("Failed to patch the libraries of {}: {}", version, e));
+            return;
+        }
+    };
+    bundle.add_json("libraries.json", &info.libraries);
+
+    let java_version = info.java_version.major_version.to_string();
+    let Some(java) = installed_java_executable(&java_version, sources.runtime_dir) else {
+        bundle.problem(format!("Java {} hasn't been downloaded", java_version));
+        return;
+    };
+
+    // `-version` prints to stderr
+    match tokio::process::Command::new(&java).arg("-version").output().await {
+        Ok(output) => {
+            let text = format!(
+                "{}\n{}{}",
+                java.display(),
+                String::from_utf8_lossy(&output.stderr),
+                String::from_utf8_lossy(&output.stdout)
+            );
+            bundle.add("java-version.txt", &text);
+        }
+        Err(e) => bundle.problem(format!("Failed to run {}: {}", java.display(), e)),
+    }
+}
+
+// Where an extension's ERM is in its repository, following maven's layout
+fn erm_location(descriptor: &str) -> Option<(String, String)> {
+    let mut parts = descriptor.split(':');
+    let (group, name, version) = (parts.next()?, parts.next()?, parts.next()?);
+
+    let file_name = format!("{}-{}-erm.json", name, version);
+    let location = format!("{}/{}/{}/{}", group.replace('.', "/"), name, version, file_name);
+
+    Some((location, file_name))
+}
+
+async fn fetch_erm(extension: &Extension, downloads: &DownloadScheduler) -> Result<(String, String), String> {
+    let (location, file_name) = erm_location(&extension.descriptor)
+        .ok_or_else(|| format!("'{}' isn't a valid descriptor", extension.descriptor))?;
+
+    let bytes = match extension.repository_type {
+        RepositoryType::LOCAL => {
+            read(PathBuf::from(&extension.repository).join(&location)).map_err(|e| e.to_string())?
+        }
+        RepositoryType::REMOTE => {
+            let url = format!("{}/{}", extension.repository.trim_end_matches('/'), location);
+            let response = downloads.get(&url).await.map_err(|e| e.to_string())?;
+
+            response.bytes().await.map_err(|e| e.to_string())?.to_vec()
+        }
+    };
+
+    Ok((file_name, String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+async fn add_extensions(bundle: &mut Bundle<'_>, sources: &BundleSources<'_>, downloads: &DownloadScheduler) {
+    let instance = sources.instance;
+
+    for extension in &instance.extensions {
+        match fetch_erm(extension, downloads).await {
+            Ok((file_name, erm)) => bundle.add(format!("extensions/{}", file_name), &erm),
+            Err(e) => bundle.problem(format!("Failed to get the ERM of {}: {}", extension.descriptor, e)),
+        }
+    }
+
+    if instance.mods.is_empty() {
+        return;
+    }
+
+    match stored_mod_extension(sources.mods_repo, &instance.mods) {
+        Ok(Some(extension)) => {
+            let extension = Extension {
+                repository: sources.mods_repo.to_string_lossy().to_string(),
+                repository_type: RepositoryType::LOCAL,
+                ..extension
+            };
+
+            match fetch_erm(&extension, downloads).await {
+                Ok((file_name, erm)) => bundle.add(format!("mods/{}", file_name), &erm),
+                Err(e) => bundle.problem(format!("Failed to read the generated mod ERM: {}", e)),
+            }
+        }
+        Ok(None) => bundle.problem("No extension has been generated for this instance's mods yet".to_string()),
+        Err(e) => bundle.problem(format!("Failed to read the generated mods: {}", e)),
+    }
+}
+
+fn add_logs(bundle: &mut Bundle<'_>, sources: &BundleSources<'_>) {
+    let instance = sources.instance;
+
+    match list_session_logs_in(sources.session_logs_dir, Some(&instance.id)) {
+        Ok(logs) => {
+            for log in logs.into_iter().take(sources.sessions) {
+                match read_session_log_in(sources.session_logs_dir, &instance.id, &log.name) {
+                    Ok(text) => bundle.add(format!("logs/{}", log.name.trim_end_matches(".gz")), &text),
+                    Err(e) => bundle.problem(format!("Failed to read session log {}: {}", log.name, e)),
+                }
+            }
+        }
+        Err(e) => bundle.problem(format!("Failed to list session logs: {}", e)),
+    }
+
+    let fatal_errors = newest_files(&instance.game_dir, |name| {
+        name.starts_with("hs_err_pid") && name.ends_with(".log")
+    });
+    let crash_reports = newest_files(&instance.game_dir.join("crash-reports"), |name| name.ends_with(".txt"));
+
+    for (folder, files) in [("crash-reports", crash_reports), ("fatal-errors", fatal_errors)] {
+        for path in files {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+            match std::fs::read(&path) {
+                Ok(bytes) => bundle.add(format!("{}/{}", folder, name), &String::from_utf8_lossy(&bytes)),
+                Err(e) => bundle.problem(format!("Failed to read {}: {}", path.display(), e)),
+            }
+        }
+    }
+}
+
+fn newest_files(dir: &Path, filter: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    let Ok(entries) = read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut files = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().to_str()?.to_string();
+            let modified = entry.metadata().ok()?.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+
+            (entry.path().is_file() && filter(&name)).then(|| (entry.path(), modified))
+        })
+        .collect::<Vec<_>>();
+    files.sort_by(|a, b| b.1.cmp(&a.1));
+
+    files.into_iter().take(MAX_CRASH_REPORTS).map(|(path, _)| path).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instances::{InstanceJvmSettings, InstanceLaunchSettings};
+    use crate::launch::redact::KnownSecrets;
+    use crate::settings::{DownloadSettings, RedactionSettings};
+    use std::fs::{create_dir_all, remove_dir_all, write};
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    #[tokio::test]
+    async fn test_diagnostic_bundle() {
+        let dir = PathBuf::from("tests/diagnostic-bundle");
+        if dir.exists() {
+            remove_dir_all(&dir).unwrap();
+        }
+
+        let game_dir = dir.join("game");
+        create_dir_all(game_dir.join("crash-reports")).unwrap();
+        write(game_dir.join("crash-reports/crash-2024-11-02_18.21.45-client.txt"), "---- Minecraft Crash Report ----").unwrap();
+        write(game_dir.join("hs_err_pid41231.log"), "# A fatal error has been detected").unwrap();
+
+        let extension_repo = dir.join("m2");
+        let erm_dir = extension_repo.join("dev/example/minimap-ext/1.0");
+        create_dir_all(&erm_dir).unwrap();
+        write(erm_dir.join("minimap-ext-1.0-erm.json"), r#"{"name":"minimap-ext"}"#).unwrap();
+
+        let session_logs_dir = dir.join("logs");
+        create_dir_all(session_logs_dir.join("test")).unwrap();
+        write(session_logs_dir.join("test/2024-11-02_18-20-00.log"), "[18:20:00] [main/INFO]: Setting user: Notch").unwrap();
+
+        let instance = Instance {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            version: "1.21.4".to_string(),
+            game_dir,
+            extensions: vec![Extension {
+                descriptor: "dev.example:minimap-ext:1.0".to_string(),
+                repository: extension_repo.to_string_lossy().to_string(),
+                repository_type: RepositoryType::LOCAL,
+            }],
+            mods: vec![],
+            jvm: InstanceJvmSettings::default(),
+            launch: InstanceLaunchSettings {
+                env: HashMap::from([("MODRINTH_TOKEN".to_string(), "mrp_hunter2".to_string())]),
+                ..InstanceLaunchSettings::default()
+            },
+            icon: None,
+        };
+
+        let config = HashMap::from([
+            ("instances".to_string(), serde_json::to_value(vec![&instance]).unwrap()),
+            ("ms_auth".to_string(), serde_json::json!({ "access_token": "eyJhbGciOi.eyJzdWIiOi.c2lnbmF0dXJl" })),
+            ("settings".to_string(), serde_json::json!({ "logging": { "launcher_config": false } })),
+        ]);
+
+        let secrets = KnownSecrets {
+            player_name: Some("Notch".to_string()),
+            ..KnownSecrets::default()
+        };
+        let redactor = Redactor::new(&RedactionSettings::default(), &secrets);
+
+        let bundle_path = dir.join("bundle.zip");
+        let problems = write_diagnostic_bundle(
+            &bundle_path,
+            BundleSources {
+                instance: &instance,
+                minecraft_dir: &dir.join("minecraft"),
+                runtime_dir: &dir.join("runtime"),
+                mods_repo: &dir.join("repo"),
+                session_logs_dir: &session_logs_dir,
+                config,
+                client_version: "1.1.2-BETA",
+                sessions: 3,
+            },
+            &redactor,
+            &DownloadScheduler::new(&DownloadSettings::default()),
+        ).await.unwrap();
+
+        // Nothing was installed for the version
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("Failed to read version 1.21.4"));
+
+        let mut zip = ZipArchive::new(File::open(&bundle_path).unwrap()).unwrap();
+        let mut names = zip.file_names().map(|it| it.to_string()).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec![
+            "config.json",
+            "crash-reports/crash-2024-11-02_18.21.45-client.txt",
+            "extensions/minimap-ext-1.0-erm.json",
+            "fatal-errors/hs_err_pid41231.log",
+            "instance.json",
+            "logs/2024-11-02_18-20-00.log",
+            "problems.txt",
+            "settings.json",
+            "system.json",
+        ]);
+
+        let mut read_entry = |name: &str| {
+            let mut text = String::new();
+            zip.by_name(name).unwrap().read_to_string(&mut text).unwrap();
+            text
+        };
+        assert!(!read_entry("config.json").contains("eyJ"));
+        for name in ["config.json", "instance.json"] {
+            let text = read_entry(name);
+            assert!(text.contains("MODRINTH_TOKEN") && !text.contains("hunter2"));
+        }
+        assert_eq!(read_entry("logs/2024-11-02_18-20-00.log"), "[18:20:00] [main/INFO]: Setting user: <player>");
+    }
+}
@@ -1,8 +1,7 @@
 use crate::extframework_dir;
 use crate::launch::ClientError;
-use crate::task::copy::copy_stream_tracking;
+use crate::task::download::{DownloadRequest, DownloadScheduler};
 use crate::task::TaskManager;
-use std::fs::{create_dir_all, File};
 use std::path::{Path, PathBuf};
 
 fn client_url(version: String) -> String {
@@ -12,12 +11,13 @@ fn client_url(version: String) -> String {
 pub async fn get_client(
     version: String,
     tasks: &mut TaskManager,
+    downloads: &DownloadScheduler,
 ) -> Result<PathBuf, ClientError> {
     let path = extframework_dir().join(format!("client-{}.jar", version));
 
     if !Path::new(path.as_os_str()).exists() {
         println!("Downloading client");
-        download_client(version, &path, tasks).await?
+        download_client(version, &path, tasks, downloads).await?
     }
     Ok(path)
 }
@@ -26,35 +26,30 @@ async fn download_client(
     version: String,
     path: &PathBuf,
     tasks: &mut TaskManager,
+    downloads: &DownloadScheduler,
 ) -> Result<(), ClientError> {
-
-    tasks.submit("Download client", |mut task| {
-        let client_url = client_url(version);
-        async move {
-            let response = reqwest::get(client_url).await
-                .map_err(ClientError::NetworkError)?;
-
-            let length = response.headers().get("Content-Length")
-                .and_then(|t| Some(t.to_str().ok()?.parse::<u64>().ok()?))
-                .unwrap_or(0u64);
-
-            println!("{}", length);
-
-            create_dir_all(path.parent().unwrap()).expect("Failed to create client dir path");
-            let mut client_file = File::create(path).expect("Failed to open client.jar file");
-            let mut stream = response.bytes_stream();
-
-            let r : Result<(), ClientError> = copy_stream_tracking(
-                &mut stream,
-                &mut client_file,
-                length,
-                &mut task.progress
-            ).await;
-            r?;
-
-            Ok::<(), ClientError>(())
-        }
-    }).await?;
+    let url = client_url(version.clone());
+
+    // The repository publishes a checksum next to every file
+    let sha1 = downloads
+        .get(&format!("{}.sha1", url))
+        .await
+        .map_err(ClientError::NetworkError)?
+        .text()
+        .await
+        .map_err(ClientError::NetworkError)?;
+
+    let request = DownloadRequest {
+        name: format!("client-{}.jar", version),
+        url,
+        path: path.clone(),
+        size: None,
+        sha1: sha1.split_whitespace().next().map(str::to_lowercase),
+    };
+
+    tasks.submit("Download client", |mut task| async move {
+        downloads.download(&request, &mut task.progress).await
+    }).await.map_err(ClientError::DownloadError)?;
 
     Ok(())
 }
@@ -94,6 +89,8 @@ mod tests {
     use crate::launch::client::{get_client, get_client_version};
     use std::fs::create_dir_all;
     use std::path::PathBuf;
+    use crate::settings::DownloadSettings;
+    use crate::task::download::DownloadScheduler;
     use crate::task::TaskManager;
     use crate::task::tests::PrintingTrackerBuilder;
 
@@ -104,7 +101,8 @@ mod tests {
         };
 
         let mut manager = TaskManager::new(Box::new(builder));
-        get_client("1.0.12-BETA".to_string(), &mut manager).await.unwrap();
+        let downloads = DownloadScheduler::new(&DownloadSettings::default());
+        get_client("1.0.12-BETA".to_string(), &mut manager, &downloads).await.unwrap();
     }
 
     #[tokio::test]
@@ -0,0 +1,332 @@
+use crate::state::Extension;
+use serde::Serialize;
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Frames from these are part of every game, so they say nothing about what caused a crash
+const PLATFORM_PACKAGES: [&str; 14] = [
+    "java.", "javax.", "jdk.", "sun.", "com.sun.", "net.minecraft.", "com.mojang.", "org.lwjgl.",
+    "io.netty.", "com.google.", "org.apache.", "it.unimi.", "dev.extframework.", "net.fabricmc.",
+];
+
+// Anything past this is very rarely useful and only makes the event bigger
+const MAX_STACK_FRAMES: usize = 64;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashSource {
+    // Written by the game to `crash-reports`
+    CrashReport,
+    // Written by the JVM when it crashes outright
+    JvmFatalError,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct JvmInfo {
+    pub java_version: Option<String>,
+    pub vm: Option<String>,
+    pub flags: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CrashSummary {
+    pub source: CrashSource,
+    pub path: PathBuf,
+    pub description: Option<String>,
+    pub exception: Option<String>,
+    pub stack_trace: Vec<String>,
+    pub jvm: JvmInfo,
+    // Extensions, packages or native libraries that show up where the crash happened
+    pub suspects: Vec<String>,
+}
+
+// Whatever the game or JVM wrote about a crash since the session started: the newest crash report,
+// and every fatal error log
+pub fn find_crashes(game_dir: &Path, since: SystemTime, extensions: &[Extension]) -> Vec<CrashSummary> {
+    let mut crashes = Vec::new();
+
+    let newest_report = written_since(&game_dir.join("crash-reports"), since)
+        .into_iter()
+        .max_by_key(|(_, modified)| *modified);
+    if let Some((path, _)) = newest_report {
+        if let Ok(text) = read_to_string(&path) {
+            crashes.push(parse_crash_report(path, &text, extensions));
+        }
+    }
+
+    for (path, _) in written_since(game_dir, since) {
+        let is_fatal_error = path
+            .file_name()
+            .and_then(|it| it.to_str())
+            .is_some_and(|name| name.starts_with("hs_err_pid") && name.ends_with(".log"));
+
+        if is_fatal_error {
+            if let Ok(text) = read_to_string(&path) {
+                crashes.push(parse_fatal_error(path, &text, extensions));
+            }
+        }
+    }
+
+    crashes
+}
+
+fn written_since(dir: &Path, since: SystemTime) -> Vec<(PathBuf, SystemTime)> {
+    let Ok(entries) = read_dir(dir) else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let modified = entry.metadata().ok()?.modified().ok()?;
+
+            (entry.path().is_file() && modified >= since).then(|| (entry.path(), modified))
+        })
+        .collect()
+}
+
+pub fn parse_crash_report(path: PathBuf, text: &str, extensions: &[Extension]) -> CrashSummary {
+    let lines = text.lines().collect::<Vec<_>>();
+
+    let description_index = lines.iter().position(|it| it.starts_with("Description: "));
+    let description = description_index.map(|i| lines[i]["Description: ".len()..].trim().to_string());
+
+    // The exception follows the description after a blank line, then its stack trace until the next one
+    let mut exception = None;
+    let mut stack_trace = Vec::new();
+    if let Some(index) = description_index {
+        let mut rest = lines[index + 1..].iter().skip_while(|it| it.trim().is_empty());
+
+        exception = rest.next().map(|it| it.trim().to_string());
+        stack_trace = rest
+            .take_while(|it| !it.trim().is_empty())
+            .map(|it| it.trim().to_string())
+            .take(MAX_STACK_FRAMES)
+            .collect();
+    }
+
+    let detail = |name: &str| {
+        let prefix = format!("{}: ", name);
+
+        lines
+            .iter()
+            .map(|it| it.trim())
+            .find(|it| it.starts_with(&prefix))
+            .map(|it| it[prefix.len()..].to_string())
+    };
+
+    CrashSummary {
+        source: CrashSource::CrashReport,
+        path,
+        description,
+        exception,
+        suspects: suspects(&stack_trace, extensions),
+        stack_trace,
+        jvm: JvmInfo {
+            java_version: detail("Java Version"),
+            vm: detail("Java VM Version"),
+            flags: detail("JVM Flags"),
+        },
+    }
+}
+
+pub fn parse_fatal_error(path: PathBuf, text: &str, extensions: &[Extension]) -> CrashSummary {
+    let lines = text.lines().collect::<Vec<_>>();
+
+    // The header is made of `#` comments, the first real one names the signal or exception
+    let header = lines
+        .iter()
+        .take_while(|it| it.starts_with('#'))
+        .map(|it| it.trim_start_matches('#').trim())
+        .filter(|it| !it.is_empty())
+        .collect::<Vec<_>>();
+
+    let header_value = |prefix: &str| {
+        header
+            .iter()
+            .find(|it| it.starts_with(prefix))
+            .map(|it| it[prefix.len()..].trim().to_string())
+    };
+
+    let description = header
+        .iter()
+        .skip_while(|it| it.starts_with("A fatal error has been detected"))
+        .next()
+        .map(|it| it.to_string());
+
+    let exception = header
+        .iter()
+        .position(|it| it.starts_with("Problematic frame:"))
+        .and_then(|i| header.get(i + 1))
+        .map(|it| it.to_string());
+
+    // Native frames also include the Java ones, they're only listed on their own when there are no
+    // native frames
+    let stack_trace = ["Native frames:", "Java frames:"]
+        .iter()
+        .find_map(|title| lines.iter().position(|it| it.starts_with(title)))
+        .map(|index| {
+            lines[index + 1..]
+                .iter()
+                .take_while(|it| !it.trim().is_empty())
+                .map(|it| it.trim().to_string())
+                .take(MAX_STACK_FRAMES)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let flags = lines
+        .iter()
+        .find(|it| it.starts_with("jvm_args:"))
+        .map(|it| it["jvm_args:".len()..].trim().to_string());
+
+    let mut suspects = suspects(&stack_trace, extensions);
+    // The library the JVM died in, `C  [libGL.so.1+0x1234]`
+    if let Some(library) = exception.as_ref().and_then(|it| native_library(it)) {
+        suspects.insert(0, library);
+    }
+
+    CrashSummary {
+        source: CrashSource::JvmFatalError,
+        path,
+        description,
+        exception,
+        stack_trace,
+        jvm: JvmInfo {
+            java_version: header_value("JRE version:"),
+            vm: header_value("Java VM:"),
+            flags,
+        },
+        suspects,
+    }
+}
+
+fn native_library(frame: &str) -> Option<String> {
+    let start = frame.find('[')? + 1;
+    let end = start + frame[start..].find(|it| it == '+' || it == ']')?;
+
+    Some(frame[start..end].to_string())
+}
+
+// The class a stack frame is in. Handles both `at a.b.C.method(C.java:1)` from the game and
+// `j  a.b.C.method()V+5` from the JVM.
+fn frame_class(frame: &str) -> Option<&str> {
+    let frame = frame
+        .strip_prefix("at ")
+        .or_else(|| frame.strip_prefix("j ").or_else(|| frame.strip_prefix("J ")))?
+        .trim();
+    let method = frame.split('(').next()?;
+    // Module prefixes like `java.base/`
+    let method = method.rsplit('/').next()?;
+
+    method.rsplit_once('.').map(|(class, _)| class)
+}
+
+fn suspects(stack_trace: &[String], extensions: &[Extension]) -> Vec<String> {
+    let mut suspects: Vec<String> = Vec::new();
+
+    for class in stack_trace.iter().filter_map(|it| frame_class(it)) {
+        let extension = extensions.iter().find(|extension| {
+            extension
+                .descriptor
+                .split(':')
+                .next()
+                .is_some_and(|group| class.starts_with(&format!("{}.", group)))
+        });
+
+        let suspect = if let Some(extension) = extension {
+            extension.descriptor.clone()
+        } else if PLATFORM_PACKAGES.iter().any(|it| class.starts_with(it)) {
+            continue;
+        } else {
+            // Most projects live under a reversed domain plus a name
+            class.split('.').take(3).collect::<Vec<_>>().join(".")
+        };
+
+        if !suspects.contains(&suspect) {
+            suspects.push(suspect);
+        }
+    }
+
+    suspects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::RepositoryType;
+
+    #[test]
+    fn test_parse_crashes() {
+        let extensions = vec![Extension {
+            descriptor: "dev.example.minimap:minimap-ext:1.0".to_string(),
+            repository: "https://repo.example.dev".to_string(),
+            repository_type: RepositoryType::REMOTE,
+        }];
+
+        let report = "---- Minecraft Crash Report ----
+// Shall we play a game?
+
+Time: 2024-11-02 18:21:45
+Description: Rendering overlay
+
+java.lang.NullPointerException: Cannot invoke \"Object.toString()\" because \"value\" is null
+\tat dev.example.minimap.render.MinimapOverlay.render(MinimapOverlay.java:42)
+\tat net.minecraft.client.gui.Gui.render(Gui.java:180)
+\tat com.other.tweaks.GuiHook.onRender(GuiHook.java:12)
+\tat java.base/java.lang.Thread.run(Thread.java:1583)
+
+A detailed walkthrough of the error, its code path and all known details is as follows:
+---------------------------------------------------------------------------------------
+
+-- System Details --
+Details:
+\tMinecraft Version: 1.21.4
+\tJava Version: 21.0.3, Microsoft
+\tJava VM Version: OpenJDK 64-Bit Server VM (mixed mode), Microsoft
+\tJVM Flags: 2 total; -Xmx2G -Xms512M
+";
+
+        let crash = parse_crash_report(PathBuf::from("crash.txt"), report, &extensions);
+        assert_eq!(crash.source, CrashSource::CrashReport);
+        assert_eq!(crash.description.as_deref(), Some("Rendering overlay"));
+        assert!(crash.exception.unwrap().starts_with("java.lang.NullPointerException"));
+        assert_eq!(crash.stack_trace.len(), 4);
+        assert_eq!(crash.jvm.java_version.as_deref(), Some("21.0.3, Microsoft"));
+        assert_eq!(crash.jvm.flags.as_deref(), Some("2 total; -Xmx2G -Xms512M"));
+        assert_eq!(crash.suspects, vec!["dev.example.minimap:minimap-ext:1.0", "com.other.tweaks"]);
+
+        let fatal_error = "#
+# A fatal error has been detected by the Java Runtime Environment:
+#
+#  SIGSEGV (0xb) at pc=0x00007f3a1c2b3e8b, pid=41231, tid=41260
+#
+# JRE version: OpenJDK Runtime Environment Zulu17.44+53-CA (17.0.8.1+1) (build 17.0.8.1+1-LTS)
+# Java VM: OpenJDK 64-Bit Server VM Zulu17.44+53-CA (17.0.8.1+1-LTS, mixed mode, sharing, tiered, compressed oops, compressed class ptrs, g1 gc, linux-amd64)
+# Problematic frame:
+# C  [libnvidia-glcore.so.535.129.03+0x11e3e8b]
+#
+
+---------------  T H R E A D  ---------------
+
+Native frames: (J=compiled Java code, j=interpreted, Vv=VM code, C=native code)
+C  [libnvidia-glcore.so.535.129.03+0x11e3e8b]
+j  org.lwjgl.opengl.GL11C.glDrawElements(IIIJ)V+0
+j  com.other.tweaks.BatchRenderer.flush()V+42
+
+---------------  P R O C E S S  ---------------
+
+jvm_args: -Xmx4G -Djava.library.path=/natives
+";
+
+        let crash = parse_fatal_error(PathBuf::from("hs_err_pid41231.log"), fatal_error, &extensions);
+        assert_eq!(crash.source, CrashSource::JvmFatalError);
+        assert!(crash.description.unwrap().starts_with("SIGSEGV (0xb)"));
+        assert_eq!(crash.exception.as_deref(), Some("C  [libnvidia-glcore.so.535.129.03+0x11e3e8b]"));
+        assert_eq!(crash.stack_trace.len(), 3);
+        assert!(crash.jvm.vm.unwrap().starts_with("OpenJDK 64-Bit Server VM"));
+        assert_eq!(crash.jvm.flags.as_deref(), Some("-Xmx4G -Djava.library.path=/natives"));
+        assert_eq!(crash.suspects, vec!["libnvidia-glcore.so.535.129.03", "com.other.tweaks"]);
+    }
+}
@@ -0,0 +1,389 @@
+use crate::launch::minecraft::{AssetObjects, Error, MinecraftEnvironment, VersionInfo};
+use crate::launch::natives::natives_dir;
+use crate::mods::{forget_mod_extensions, generated_mods_dir, reachable_mod_extensions};
+use crate::state::Mod;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::{read, read_dir, remove_dir, remove_dir_all, remove_file};
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum GarbageCategory {
+    Libraries,
+    Assets,
+    Versions,
+    Natives,
+    Runtimes,
+    ClientJars,
+    GeneratedMods,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct CategoryReport {
+    pub paths: Vec<PathBuf>,
+    pub size: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct GarbageReport {
+    pub categories: HashMap<GarbageCategory, CategoryReport>,
+    // Installed versions that couldn't be read. Whatever they depend on is unknown, so libraries,
+    // assets, natives and runtimes are left alone until they are fixed or removed.
+    pub unreadable_versions: Vec<String>,
+    // Across every category
+    pub size: u64,
+    pub deleted: bool,
+}
+
+impl GarbageReport {
+    fn add(&mut self, category: GarbageCategory, path: PathBuf) -> io::Result<()> {
+        let report = self.categories.entry(category).or_default();
+        let size = disk_size(&path)?;
+
+        report.size += size;
+        report.paths.push(path);
+        self.size += size;
+
+        Ok(())
+    }
+}
+
+// Where everything the GC looks at lives
+pub struct GarbageRoots<'a> {
+    pub minecraft_dir: &'a Path,
+    pub runtime_dir: &'a Path,
+    pub client_dir: &'a Path,
+    pub mods_repo: &'a Path,
+    pub client_version: &'a str,
+    // Every mod list an extension may still be generated for
+    pub mod_sets: &'a [Vec<Mod>],
+}
+
+// Everything installed versions can still reach
+#[derive(Default)]
+struct Reachable {
+    files: HashSet<PathBuf>,
+    natives: HashSet<PathBuf>,
+    java_versions: HashSet<String>,
+}
+
+impl Reachable {
+    fn add_version(&mut self, path: &Path, version: &str) -> Result<(), Error> {
+        let chain = MinecraftEnvironment::installed_version_chain(path, version)?;
+        let merged = MinecraftEnvironment::merge_version_chain(chain.clone())?;
+
+        // Library patches swap out some libraries, and whatever else uses this directory will
+        // still want the originals
+        let unpatched: VersionInfo = serde_json::from_value(merged).map_err(Error::Serde)?;
+        let patched = MinecraftEnvironment::merge_version_info(chain)?;
+
+        for info in [&unpatched, &patched] {
+            let natives = MinecraftEnvironment::native_artifacts(path, info);
+
+            self.files.extend(
+                MinecraftEnvironment::library_artifacts(path, info)
+                    .into_iter()
+                    .chain(natives.iter().map(|native| native.artifact.clone()))
+                    .map(|artifact| artifact.path),
+            );
+            self.natives.insert(natives_dir(path, version, &natives));
+        }
+
+        let index = MinecraftEnvironment::asset_index_artifact(path, &patched);
+        // An index that was never downloaded has no objects to keep
+        if let Ok(bytes) = read(&index.path) {
+            let objects: AssetObjects = serde_json::from_slice(&bytes).map_err(Error::Serde)?;
+
+            self.files.extend(
+                MinecraftEnvironment::asset_artifacts(path, &objects)
+                    .into_iter()
+                    .map(|artifact| artifact.path),
+            );
+        }
+        self.files.insert(index.path);
+
+        self.java_versions.insert(patched.java_version.major_version.to_string());
+
+        Ok(())
+    }
+}
+
+// Works out what nothing installed refers to anymore. With `delete` it is removed as well, otherwise
+// the report only says what would be.
+pub fn collect_garbage(roots: &GarbageRoots, delete: bool) -> io::Result<GarbageReport> {
+    let mut report = GarbageReport {
+        categories: HashMap::new(),
+        unreadable_versions: vec![],
+        size: 0,
+        deleted: delete,
+    };
+
+    let path = roots.minecraft_dir;
+    let mut reachable = Reachable::default();
+
+    for entry in list_dir(&path.join("versions"))? {
+        // Versions are directories, anything else in there (like the cached manifest) isn't ours
+        if !entry.is_dir() {
+            continue;
+        }
+
+        let Some(version) = entry.file_name().and_then(|it| it.to_str()).map(str::to_string) else {
+            continue;
+        };
+
+        if !MinecraftEnvironment::version_json_path(path, &version).exists() {
+            // Never finished installing, or its JSON was deleted
+            report.add(GarbageCategory::Versions, entry)?;
+        } else if reachable.add_version(path, &version).is_err() {
+            report.unreadable_versions.push(version);
+        }
+    }
+
+    if report.unreadable_versions.is_empty() {
+        for file in list_files(&path.join("libraries"))? {
+            if !reachable.files.contains(&file) {
+                report.add(GarbageCategory::Libraries, file)?;
+            }
+        }
+
+        let assets = path.join("assets");
+        for file in list_files(&assets.join("objects"))?
+            .into_iter()
+            .chain(list_files(&assets.join("indexes"))?) {
+            if !reachable.files.contains(&file) {
+                report.add(GarbageCategory::Assets, file)?;
+            }
+        }
+
+        for entry in list_dir(&path.join("natives"))? {
+            if !reachable.natives.contains(&entry) {
+                report.add(GarbageCategory::Natives, entry)?;
+            }
+        }
+
+        // Runtimes are unpacked into `jre-<major>`, anything else there is a leftover archive
+        let runtimes = reachable.java_versions
+            .iter()
+            .map(|version| roots.runtime_dir.join(format!("jre-{}", version)))
+            .collect::<HashSet<_>>();
+        for entry in list_dir(roots.runtime_dir)? {
+            if file_name_matches(&entry, "jre-", "") && !runtimes.contains(&entry) {
+                report.add(GarbageCategory::Runtimes, entry)?;
+            }
+        }
+    }
+
+    let client_jar = roots.client_dir.join(format!("client-{}.jar", roots.client_version));
+    for entry in list_dir(roots.client_dir)? {
+        let is_client = file_name_matches(&entry, "client-", ".jar")
+            || file_name_matches(&entry, "client-", ".jar.part");
+
+        if is_client && entry != client_jar {
+            report.add(GarbageCategory::ClientJars, entry)?;
+        }
+    }
+
+    let generated = generated_mods_dir(roots.mods_repo);
+    let reachable_mods = reachable_mod_extensions(roots.mods_repo, roots.mod_sets)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    for entry in list_dir(&generated)? {
+        let is_reachable = entry
+            .file_name()
+            .and_then(|it| it.to_str())
+            .is_some_and(|name| reachable_mods.contains(name));
+
+        if !is_reachable {
+            report.add(GarbageCategory::GeneratedMods, entry)?;
+        }
+    }
+
+    if delete {
+        // Forgotten first, so a failure part way through never leaves the store pointing at
+        // something that's gone
+        forget_mod_extensions(roots.mods_repo, &reachable_mods)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+        for (category, category_report) in &report.categories {
+            // These are nested directories that are otherwise left behind empty
+            let root = match category {
+                GarbageCategory::Libraries => Some(path.join("libraries")),
+                GarbageCategory::Assets => Some(path.join("assets")),
+                _ => None,
+            };
+
+            for path in &category_report.paths {
+                remove(path)?;
+
+                if let Some(root) = &root {
+                    remove_empty_parents(path, root);
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+fn file_name_matches(path: &Path, prefix: &str, suffix: &str) -> bool {
+    path.file_name()
+        .and_then(|it| it.to_str())
+        .is_some_and(|name| name.starts_with(prefix) && name.ends_with(suffix))
+}
+
+// A directory that doesn't exist has nothing in it
+fn list_dir(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    read_dir(path)?
+        .map(|entry| entry.map(|it| it.path()))
+        .collect()
+}
+
+fn list_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in list_dir(path)? {
+        if entry.is_dir() {
+            files.extend(list_files(&entry)?);
+        } else {
+            files.push(entry);
+        }
+    }
+
+    Ok(files)
+}
+
+fn disk_size(path: &Path) -> io::Result<u64> {
+    if path.is_dir() {
+        list_files(path)?
+            .iter()
+            .map(|file| file.metadata().map(|it| it.len()))
+            .sum()
+    } else {
+        Ok(path.metadata()?.len())
+    }
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    let result = if path.is_dir() {
+        remove_dir_all(path)
+    } else {
+        remove_file(path)
+    };
+
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+// Empty directories left behind up to, but not including, the root
+fn remove_empty_parents(path: &Path, root: &Path) {
+    let mut current = path.parent();
+
+    while let Some(dir) = current {
+        if dir == root || !dir.starts_with(root) || remove_dir(dir).is_err() {
+            break;
+        }
+        current = dir.parent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::launch::manifest::manifest_path;
+    use std::fs::{create_dir_all, write};
+
+    fn touch(path: &Path) {
+        create_dir_all(path.parent().unwrap()).unwrap();
+        write(path, b"data").unwrap();
+    }
+
+    #[test]
+    fn test_collect_garbage() {
+        let root = PathBuf::from("tests/gc");
+        let _ = remove_dir_all(&root);
+
+        let minecraft = root.join("minecraft");
+        let runtime = root.join("runtime");
+        let client = root.join("client");
+        let repo = root.join("repo");
+
+        let json = r#"{
+            "id": "custom",
+            "type": "release",
+            "mainClass": "net.minecraft.client.main.Main",
+            "assets": "legacy",
+            "assetIndex": {"id": "legacy", "sha1": "", "size": 0, "totalSize": 0, "url": ""},
+            "downloads": {},
+            "javaVersion": {"component": "jre-legacy", "majorVersion": 17},
+            "releaseTime": "2024-01-01T00:00:00+00:00",
+            "time": "2024-01-01T00:00:00+00:00",
+            "libraries": [
+                {"name": "com.example:kept:1.0"}
+            ]
+        }"#;
+        touch(&minecraft.join("versions/custom/custom.json"));
+        write(minecraft.join("versions/custom/custom.json"), json).unwrap();
+        create_dir_all(minecraft.join("versions/partial")).unwrap();
+        let manifest = manifest_path(&minecraft);
+        touch(&manifest);
+
+        let kept = minecraft.join("libraries/com/example/kept/1.0/kept-1.0.jar");
+        let stale = minecraft.join("libraries/com/example/stale/0.9/stale-0.9.jar");
+        touch(&kept);
+        touch(&stale);
+
+        let object = minecraft.join("assets/objects/ab/abcdef");
+        touch(&object);
+        touch(&minecraft.join("assets/indexes/legacy.json"));
+        write(
+            minecraft.join("assets/indexes/legacy.json"),
+            r#"{"objects": {"icon.png": {"hash": "ab1234", "size": 4}}}"#,
+        ).unwrap();
+
+        touch(&runtime.join("jre-17/bin/java"));
+        touch(&runtime.join("jre-8/bin/java"));
+        touch(&client.join("client-1.0.jar"));
+        touch(&client.join("client-2.0.jar"));
+        touch(&repo.join("dev/extframework/generated/mods-abc/1/mods-abc-1-erm.json"));
+
+        let roots = GarbageRoots {
+            minecraft_dir: &minecraft,
+            runtime_dir: &runtime,
+            client_dir: &client,
+            mods_repo: &repo,
+            client_version: "2.0",
+            mod_sets: &[],
+        };
+
+        let report = collect_garbage(&roots, false).unwrap();
+        assert!(report.unreadable_versions.is_empty());
+
+        let paths = |category| report.categories.get(&category).map(|it| it.paths.clone()).unwrap_or_default();
+        assert_eq!(paths(GarbageCategory::Libraries), vec![stale.clone()]);
+        assert_eq!(paths(GarbageCategory::Assets), vec![object.clone()]);
+        assert_eq!(paths(GarbageCategory::Versions), vec![minecraft.join("versions/partial")]);
+        assert_eq!(paths(GarbageCategory::Runtimes), vec![runtime.join("jre-8")]);
+        assert_eq!(paths(GarbageCategory::ClientJars), vec![client.join("client-1.0.jar")]);
+        assert_eq!(paths(GarbageCategory::GeneratedMods), vec![repo.join("dev/extframework/generated/mods-abc")]);
+        assert_eq!(report.size, 5 * 4);
+        assert!(stale.exists());
+
+        collect_garbage(&roots, true).unwrap();
+        assert!(!stale.exists());
+        assert!(!minecraft.join("libraries/com/example/stale").exists());
+        assert!(kept.exists());
+        assert!(runtime.join("jre-17").exists());
+        assert!(!runtime.join("jre-8").exists());
+        assert!(client.join("client-2.0.jar").exists());
+        // Needed to launch offline
+        assert!(manifest.exists());
+        assert_eq!(collect_garbage(&roots, false).unwrap().size, 0);
+    }
+}
@@ -0,0 +1,165 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+// Long enough to sync a modpack, short enough that a stuck hook doesn't hang the launch forever
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+// How much of a failed hook's output is shown
+const MAX_OUTPUT_LINES: usize = 50;
+
+// Shell commands run around the game, with the instance's details in their environment
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct LaunchHooks {
+    pub pre_launch: Option<String>,
+    pub post_exit: Option<String>,
+    pub env: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+pub struct HookOutput {
+    pub status: Option<ExitStatus>,
+    // Stdout then stderr, only the last lines
+    pub output: String,
+}
+
+impl HookOutput {
+    pub fn success(&self) -> bool {
+        self.status.is_some_and(|it| it.success())
+    }
+
+    pub fn describe(&self, name: &str) -> String {
+        let status = match self.status.and_then(|it| it.code()) {
+            Some(code) => format!("exited with code {}", code),
+            None if self.status.is_none() => format!("timed out after {} seconds", HOOK_TIMEOUT.as_secs()),
+            None => "was killed".to_string(),
+        };
+
+        if self.output.is_empty() {
+            format!("The {} hook {}", name, status)
+        } else {
+            format!("The {} hook {}:\n{}", name, status, self.output)
+        }
+    }
+}
+
+impl LaunchHooks {
+    pub async fn run(
+        &self,
+        hook: &str,
+        working_dir: &Path,
+        extra_env: &[(&str, String)],
+    ) -> io::Result<HookOutput> {
+        let mut command = if cfg!(target_os = "windows") {
+            let mut command = Command::new("cmd");
+            command.arg("/C").arg(hook);
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(hook);
+            command
+        };
+
+        let child = command
+            .current_dir(working_dir)
+            .envs(&self.env)
+            .envs(extra_env.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let output = match timeout(HOOK_TIMEOUT, child.wait_with_output()).await {
+            Ok(output) => output?,
+            Err(_) => {
+                return Ok(HookOutput {
+                    status: None,
+                    output: String::new(),
+                })
+            }
+        };
+
+        let text = String::from_utf8_lossy(&output.stdout).to_string() + &String::from_utf8_lossy(&output.stderr);
+        let lines = text.lines().collect::<Vec<_>>();
+
+        Ok(HookOutput {
+            status: Some(output.status),
+            output: lines[lines.len().saturating_sub(MAX_OUTPUT_LINES)..].join("\n"),
+        })
+    }
+}
+
+// Splits a command line the way a shell would, minus everything but quoting and escapes
+pub fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => match chars.next() {
+                Some(escaped) => word.get_or_insert_with(String::new).push(escaped),
+                None => return Err("The command ends with an unfinished escape".to_string()),
+            },
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err("The command has an unclosed quote".to_string());
+    }
+    words.extend(word);
+
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hooks() {
+        assert_eq!(split_command("nice -n 10").unwrap(), vec!["nice", "-n", "10"]);
+        assert_eq!(
+            split_command(r#"taskset  -c '0-3' "my wrapper" a\ b """#).unwrap(),
+            vec!["taskset", "-c", "0-3", "my wrapper", "a b", ""]
+        );
+        assert!(split_command("strace -o 'trace").is_err());
+        assert!(split_command("").unwrap().is_empty());
+
+        if cfg!(target_os = "windows") {
+            return;
+        }
+
+        let hooks = LaunchHooks {
+            pre_launch: None,
+            post_exit: None,
+            env: HashMap::from([("YAKCLIENT_INSTANCE_ID".to_string(), "test".to_string())]),
+        };
+        let dir = std::env::temp_dir();
+
+        let output = hooks
+            .run("echo $YAKCLIENT_INSTANCE_ID $YAKCLIENT_EXIT_CODE", &dir, &[("YAKCLIENT_EXIT_CODE", "0".to_string())])
+            .await
+            .unwrap();
+        assert!(output.success());
+        assert_eq!(output.output, "test 0");
+
+        let output = hooks.run("echo missing mods >&2; exit 2", &dir, &[]).await.unwrap();
+        assert!(!output.success());
+        assert_eq!(output.describe("pre-launch"), "The pre-launch hook exited with code 2:\nmissing mods");
+    }
+}
@@ -1,12 +1,13 @@
-use crate::launch::java::JreSetupError::{IOError, NetworkError, UnsuccessfulZuluQuery, ZipError};
-use futures::StreamExt;
+use crate::launch::java::JreSetupError::{DownloadError, IOError, NetworkError, UnsuccessfulZuluQuery, ZipError};
+use crate::task::download::{self, DownloadFailure, DownloadRequest, DownloadScheduler};
+use crate::task::TaskManager;
+use crate::util::hash::sha256_file;
 use serde::Deserialize;
 use std::fmt::{Debug, Display, Formatter};
 use std::fs::{create_dir_all, File};
 use std::{fs, io};
-use std::io::Cursor;
-use std::path::PathBuf;
-use std::process::Command;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
 use flate2::read::GzDecoder;
 use tar::Archive;
 use zip::ZipArchive;
@@ -14,6 +15,7 @@ use zip::ZipArchive;
 #[derive(Debug)]
 pub enum JreSetupError {
     NetworkError(reqwest::Error),
+    DownloadError(DownloadFailure),
     IOError(io::Error),
     ZipError(zip::result::ZipError),
     UnsuccessfulZuluQuery,
@@ -23,6 +25,7 @@ impl Display for JreSetupError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let message = match self {
             JreSetupError::NetworkError(it) => it.to_string(),
+            DownloadError(it) => it.to_string(),
             IOError(it) => it.to_string(),
             ZipError(it) => it.to_string(),
             UnsuccessfulZuluQuery => "Failed to query Zulu for an appropriate JDK to download!".to_string()
@@ -32,53 +35,106 @@ impl Display for JreSetupError {
     }
 }
 
-async fn download_jre(
-    version: &str,
-    os_name: &str,
-    os_arch: &str,
-    path: PathBuf,
-) -> Result<PathBuf, JreSetupError> {
+// The names Zulu's API uses for the platform the launcher is running on
+pub fn zulu_os_name() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "mac"
+    } else {
+        "linux"
+    }
+}
+
+pub fn zulu_os_arch() -> &'static str {
+    if cfg!(target_arch = "x86_64") {
+        "x64"
+    } else if cfg!(target_arch = "aarch64") {
+        "aarch64"
+    } else {
+        "x64"
+    }
+}
+
+fn java_executable_path(version: &str, os_name: &str, path: &Path) -> PathBuf {
     let jre_path = path.join(format!("jre-{}", version));
 
-    let java_command_path = if os_name == "windows" {
-        jre_path.to_path_buf().join("bin").join("java.exe")
+    if os_name == "windows" {
+        jre_path.join("bin").join("java.exe")
     } else {
         jre_path
-            .to_path_buf()
             .join(format!("zulu-{}.jre", version))
             .join("Contents")
             .join("Home")
             .join("bin")
             .join("java")
-    };
+    }
+}
+
+// The runtime for a Java version if it has been downloaded already
+pub fn installed_java_executable(version: &str, path: &Path) -> Option<PathBuf> {
+    Some(java_executable_path(version, zulu_os_name(), path)).filter(|it| it.exists())
+}
+
+async fn download_jre(
+    version: &str,
+    os_name: &str,
+    os_arch: &str,
+    path: PathBuf,
+    tasks: &mut TaskManager,
+    downloads: &DownloadScheduler,
+) -> Result<PathBuf, JreSetupError> {
+    let jre_path = path.join(format!("jre-{}", version));
+    let java_command_path = java_executable_path(version, os_name, &path);
 
     if java_command_path.exists() {
         return Ok(java_command_path);
     }
 
-    let client = reqwest::Client::new();
-
-    let url = get_download_url(
-        &client,
+    let package = get_package(
+        downloads,
         version, os_name, os_arch,
     ).await?;
 
-    let bytes = client.get(url)
-        .send()
-        .await
-        .map_err(|it| NetworkError(it))?
-        .bytes()
-        .await
-        .map_err(|it| NetworkError(it))?;
+    let archive_path = path.join(format!("jre-{}.zip", version));
+    let request = DownloadRequest {
+        name: format!("jre-{}.zip", version),
+        url: package.download_url,
+        path: archive_path.clone(),
+        size: Some(package.size),
+        sha1: None,
+    };
+
+    let (name, url) = (request.name.clone(), request.url.clone());
+    tasks.submit(format!("Download Java {}", version), |mut task| async move {
+        downloads.download(&request, &mut task.progress).await
+    }).await.map_err(DownloadError)?;
+
+    // The downloader only knows SHA-1, so the archive is checked once it's complete
+    let actual = sha256_file(&archive_path).map_err(IOError)?;
+    if !actual.eq_ignore_ascii_case(&package.sha256_hash) {
+        fs::remove_file(&archive_path).map_err(IOError)?;
+
+        return Err(DownloadError(DownloadFailure {
+            name,
+            url,
+            error: download::DownloadError::ChecksumMismatch {
+                expected: package.sha256_hash,
+                actual,
+            },
+        }));
+    }
 
-    let cursor = Cursor::new(bytes);
+    let archive = File::open(&archive_path).map_err(IOError)?;
 
     // if os_name == "windows" {
-        extract_zip(jre_path, cursor)?;
+        extract_zip(jre_path, archive)?;
     // } else {
-    //     extract_tar_gz(jre_path, cursor)?;
+    //     extract_tar_gz(jre_path, archive)?;
     // }
 
+    fs::remove_file(&archive_path).map_err(IOError)?;
+
     #[cfg(target_os = "macos")] {
         use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
 
@@ -95,15 +151,23 @@ async fn download_jre(
 
 #[derive(Deserialize)]
 struct ZuluJreResponse {
+    package_uuid: String,
+}
+
+// Only the package's own page has its size and checksum, the search leaves them out
+#[derive(Deserialize)]
+struct ZuluPackage {
     download_url: String,
+    size: u64,
+    sha256_hash: String,
 }
 
-async fn get_download_url(
-    client: &reqwest::Client,
+async fn get_package(
+    downloads: &DownloadScheduler,
     version: &str,
     os_name: &str,
     os_arch: &str,
-) -> Result<String, JreSetupError> {
+) -> Result<ZuluPackage, JreSetupError> {
 
     let download_type = if cfg!(target_os = "windows") {
         "zip"
@@ -118,19 +182,31 @@ async fn get_download_url(
 
     println!("Downloading {}", url);
 
-    let response = client.get(&url).send().await.map_err(NetworkError)?;
+    let response = downloads.get(&url).await.map_err(|e| {
+        if e.is_status() {
+            UnsuccessfulZuluQuery
+        } else {
+            NetworkError(e)
+        }
+    })?;
 
-    if response.status().is_success() {
-        let res: Vec<ZuluJreResponse> = response.json().await.map_err(NetworkError)?;
+    let res: Vec<ZuluJreResponse> = response.json().await.map_err(NetworkError)?;
+    let uuid = &res.get(0).ok_or(UnsuccessfulZuluQuery)?.package_uuid;
 
-        Ok(res.get(0).ok_or(UnsuccessfulZuluQuery)?.download_url.clone())
-    } else {
-        Err(UnsuccessfulZuluQuery)
-    }
+    let url = format!("https://api.azul.com/metadata/v1/zulu/packages/{}", uuid);
+    let response = downloads.get(&url).await.map_err(|e| {
+        if e.is_status() {
+            UnsuccessfulZuluQuery
+        } else {
+            NetworkError(e)
+        }
+    })?;
+
+    response.json().await.map_err(NetworkError)
 }
 
-fn extract_zip(jre_path: PathBuf, cursor: Cursor<bytes::Bytes>) -> Result<(), JreSetupError> {
-    let mut zip = ZipArchive::new(cursor).map_err(ZipError)?;
+fn extract_zip(jre_path: PathBuf, archive: impl Read + Seek) -> Result<(), JreSetupError> {
+    let mut zip = ZipArchive::new(archive).map_err(ZipError)?;
 
     for i in 0..zip.len() {
         let mut file = zip.by_index(i).map_err(ZipError)?;
@@ -156,8 +232,8 @@ fn extract_zip(jre_path: PathBuf, cursor: Cursor<bytes::Bytes>) -> Result<(), Jr
     }
     Ok(())
 }
-fn extract_tar_gz(jre_path: PathBuf, cursor: Cursor<bytes::Bytes>) -> Result<(), JreSetupError> {
-    let tar = GzDecoder::new(cursor);
+fn extract_tar_gz(jre_path: PathBuf, archive: impl Read) -> Result<(), JreSetupError> {
+    let tar = GzDecoder::new(archive);
     let mut archive = Archive::new(tar);
 
     archive
@@ -180,20 +256,24 @@ fn extract_tar_gz(jre_path: PathBuf, cursor: Cursor<bytes::Bytes>) -> Result<(),
     Ok(())
 }
 
-pub async fn get_java_command(
+pub async fn get_java_executable(
     version: &str,
     os_name: &str,
     os_arch: &str,
     path: PathBuf,
-) -> Result<Command, JreSetupError> {
-    let path = download_jre(version, os_name, os_arch, path).await?;
-
-    Ok(Command::new(path))
+    tasks: &mut TaskManager,
+    downloads: &DownloadScheduler,
+) -> Result<PathBuf, JreSetupError> {
+    download_jre(version, os_name, os_arch, path, tasks, downloads).await
 }
 
 #[cfg(test)]
 mod tests {
     use crate::launch::java::download_jre;
+    use crate::settings::DownloadSettings;
+    use crate::task::download::DownloadScheduler;
+    use crate::task::TaskManager;
+    use crate::task::tests::PrintingTrackerBuilder;
     use std::path::PathBuf;
     use tokio::fs::create_dir_all;
 
@@ -215,7 +295,11 @@ mod tests {
         };
         let buf = PathBuf::from("jres");
         create_dir_all(&buf).await.unwrap();
-        let path = download_jre("21", os_name, os_arch, buf).await.unwrap();
+        let mut manager = TaskManager::new(Box::new(PrintingTrackerBuilder {
+            path: PathBuf::from("tests").join("jre-test"),
+        }));
+        let downloads = DownloadScheduler::new(&DownloadSettings::default());
+        let path = download_jre("21", os_name, os_arch, buf, &mut manager, &downloads).await.unwrap();
 
         println!("{:?}", path);
     }
@@ -224,7 +308,11 @@ mod tests {
     async fn test_windows_jre() {
         let buf = PathBuf::from("jres");
         create_dir_all(&buf).await.unwrap();
-        let path = download_jre("21", "windows", "x64", buf).await.unwrap();
+        let mut manager = TaskManager::new(Box::new(PrintingTrackerBuilder {
+            path: PathBuf::from("tests").join("jre-test"),
+        }));
+        let downloads = DownloadScheduler::new(&DownloadSettings::default());
+        let path = download_jre("21", "windows", "x64", buf, &mut manager, &downloads).await.unwrap();
 
         println!("{:?}", path);
     }
@@ -249,7 +337,11 @@ mod tests {
 
         let buf = PathBuf::from("jres");
         create_dir_all(&buf).await.unwrap();
-        let path = download_jre("8", os_name, os_arch, buf).await.unwrap();
+        let mut manager = TaskManager::new(Box::new(PrintingTrackerBuilder {
+            path: PathBuf::from("tests").join("jre-test"),
+        }));
+        let downloads = DownloadScheduler::new(&DownloadSettings::default());
+        let path = download_jre("8", os_name, os_arch, buf, &mut manager, &downloads).await.unwrap();
 
         println!("{:?}", path);
     }
@@ -0,0 +1,270 @@
+use crate::instances::InstanceJvmSettings;
+use crate::settings::{JvmPreset, JvmSettings};
+use std::fs::read_to_string;
+use std::process::Command;
+
+// Below this the game doesn't get as far as the title screen
+pub const MIN_MEMORY: u32 = 512;
+// Used when the system's memory can't be found out
+const FALLBACK_MEMORY: u32 = 4096;
+
+// Flags Mojang's launcher passes along with G1
+const G1_FLAGS: &[&str] = &[
+    "-XX:+UseG1GC",
+    "-XX:+UnlockExperimentalVMOptions",
+    "-XX:G1NewSizePercent=20",
+    "-XX:G1ReservePercent=20",
+    "-XX:MaxGCPauseMillis=50",
+    "-XX:G1HeapRegionSize=32M",
+];
+
+const ZGC_FLAGS: &[&str] = &["-XX:+UseZGC"];
+
+// https://docs.papermc.io/paper/aikars-flags
+const AIKAR_FLAGS: &[&str] = &[
+    "-XX:+UseG1GC",
+    "-XX:+ParallelRefProcEnabled",
+    "-XX:MaxGCPauseMillis=200",
+    "-XX:+UnlockExperimentalVMOptions",
+    "-XX:+DisableExplicitGC",
+    "-XX:+AlwaysPreTouch",
+    "-XX:G1NewSizePercent=30",
+    "-XX:G1MaxNewSizePercent=40",
+    "-XX:G1HeapRegionSize=8M",
+    "-XX:G1ReservePercent=20",
+    "-XX:G1HeapWastePercent=5",
+    "-XX:G1MixedGCCountTarget=4",
+    "-XX:InitiatingHeapOccupancyPercent=15",
+    "-XX:G1MixedGCLiveThresholdPercent=90",
+    "-XX:SurvivorRatio=32",
+    "-XX:+PerfDisableSharedMem",
+    "-XX:MaxTenuringThreshold=1",
+];
+
+impl JvmPreset {
+    pub fn flags(&self) -> &'static [&'static str] {
+        match self {
+            JvmPreset::None => &[],
+            JvmPreset::G1 => G1_FLAGS,
+            JvmPreset::Zgc => ZGC_FLAGS,
+            JvmPreset::Aikar => AIKAR_FLAGS,
+        }
+    }
+
+    // The oldest Java the flags work on
+    pub fn min_java_version(&self) -> i32 {
+        match self {
+            JvmPreset::Zgc => 15,
+            _ => 8,
+        }
+    }
+}
+
+// The global settings with an instance's overrides applied, in megabytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct JvmOptions {
+    pub min_memory: Option<u32>,
+    pub max_memory: u32,
+    pub preset: JvmPreset,
+    pub args: Vec<String>,
+    pub game_args: Vec<String>,
+}
+
+impl JvmOptions {
+    // Memory and the preset are replaced by the instance's when it sets them, its arguments come after
+    // the global ones so they win wherever the JVM takes the last occurrence
+    pub fn resolve(global: &JvmSettings, instance: &InstanceJvmSettings, total_memory: Option<u64>) -> Self {
+        JvmOptions {
+            min_memory: instance.min_memory.or(global.min_memory),
+            max_memory: instance
+                .max_memory
+                .or(global.max_memory)
+                .unwrap_or_else(|| suggested_max_memory(total_memory)),
+            preset: instance.preset.unwrap_or(global.preset),
+            args: global.args.iter().chain(instance.args.iter()).cloned().collect(),
+            game_args: global.game_args.iter().chain(instance.game_args.iter()).cloned().collect(),
+        }
+    }
+
+    pub fn validate(&self, total_memory: Option<u64>, java_version: Option<i32>) -> Result<(), String> {
+        check_memory(self.min_memory, Some(self.max_memory))?;
+
+        if let Some(total) = total_memory {
+            if self.max_memory as u64 > total {
+                return Err(format!(
+                    "The maximum memory ({} MB) is more than this system has ({} MB)",
+                    self.max_memory, total
+                ));
+            }
+        }
+
+        check_args(&self.args, &self.game_args)?;
+
+        // The heap size and collector have their own settings, setting them twice is almost always a mistake
+        if let Some(arg) = self.args.iter().find(|it| it.starts_with("-Xmx")) {
+            return Err(format!("Set the maximum memory instead of passing '{}'", arg));
+        }
+        if self.min_memory.is_some() {
+            if let Some(arg) = self.args.iter().find(|it| it.starts_with("-Xms")) {
+                return Err(format!("Set the minimum memory instead of passing '{}'", arg));
+            }
+        }
+        if self.preset != JvmPreset::None {
+            if let Some(arg) = self.args.iter().find(|it| is_gc_selection(it)) {
+                return Err(format!("'{}' picks a different garbage collector than the preset", arg));
+            }
+        }
+
+        if let Some(java_version) = java_version {
+            if java_version < self.preset.min_java_version() {
+                return Err(format!(
+                    "The {:?} preset needs Java {} or newer, this version runs on Java {}",
+                    self.preset,
+                    self.preset.min_java_version(),
+                    java_version
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Added after the version's own JVM arguments
+    pub fn jvm_arguments(&self) -> Vec<String> {
+        let mut arguments = Vec::new();
+
+        if let Some(min_memory) = self.min_memory {
+            arguments.push(format!("-Xms{}M", min_memory));
+        }
+        arguments.push(format!("-Xmx{}M", self.max_memory));
+        arguments.extend(self.preset.flags().iter().map(|it| it.to_string()));
+        arguments.extend(self.args.iter().cloned());
+
+        arguments
+    }
+}
+
+pub fn check_memory(min_memory: Option<u32>, max_memory: Option<u32>) -> Result<(), String> {
+    for memory in [min_memory, max_memory].into_iter().flatten() {
+        if memory < MIN_MEMORY {
+            return Err(format!("The game needs at least {} MB of memory", MIN_MEMORY));
+        }
+    }
+
+    if let (Some(min), Some(max)) = (min_memory, max_memory) {
+        if min > max {
+            return Err(format!("The minimum memory ({} MB) is more than the maximum ({} MB)", min, max));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn check_args(args: &[String], game_args: &[String]) -> Result<(), String> {
+    // Anything else would be taken as the main class
+    if let Some(arg) = args.iter().find(|it| !it.starts_with('-')) {
+        return Err(format!("'{}' isn't a JVM option, they start with '-'", arg));
+    }
+
+    if args.iter().chain(game_args.iter()).any(|it| it.trim().is_empty()) {
+        return Err("Arguments can't be empty".to_string());
+    }
+
+    Ok(())
+}
+
+fn is_gc_selection(arg: &str) -> bool {
+    arg.starts_with("-XX:+Use") && arg.ends_with("GC")
+}
+
+// Half of the system's memory, between 2 and 8 GB, and never more than three quarters of it
+pub fn suggested_max_memory(total_memory: Option<u64>) -> u32 {
+    match total_memory {
+        Some(total) => (total / 2).clamp(2048, 8192).min(total * 3 / 4).max(MIN_MEMORY as u64) as u32,
+        None => FALLBACK_MEMORY,
+    }
+}
+
+// In megabytes. There is no portable way to ask, so it goes through whatever each platform ships with.
+pub fn total_memory() -> Option<u64> {
+    if cfg!(target_os = "windows") {
+        let output = Command::new("powershell")
+            .arg("-NoProfile")
+            .arg("-Command")
+            .arg("(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory")
+            .output()
+            .ok()?;
+
+        String::from_utf8_lossy(&output.stdout).trim().parse::<u64>().ok().map(|it| it / 1024 / 1024)
+    } else if cfg!(target_os = "macos") {
+        let output = Command::new("sysctl").arg("-n").arg("hw.memsize").output().ok()?;
+
+        String::from_utf8_lossy(&output.stdout).trim().parse::<u64>().ok().map(|it| it / 1024 / 1024)
+    } else {
+        // MemTotal:       16318128 kB
+        read_to_string("/proc/meminfo")
+            .ok()?
+            .lines()
+            .find_map(|it| it.strip_prefix("MemTotal:"))
+            .and_then(|it| it.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+            .map(|it| it / 1024)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jvm_options() {
+        assert_eq!(suggested_max_memory(Some(16384)), 8192);
+        assert_eq!(suggested_max_memory(Some(6144)), 3072);
+        assert_eq!(suggested_max_memory(Some(2048)), 1536);
+        assert_eq!(suggested_max_memory(None), 4096);
+
+        let global = JvmSettings {
+            min_memory: Some(1024),
+            max_memory: None,
+            preset: JvmPreset::G1,
+            args: vec!["-Dglobal=true".to_string()],
+            game_args: vec!["--fullscreen".to_string()],
+        };
+        let instance = InstanceJvmSettings {
+            args: vec!["-Dinstance=true".to_string()],
+            max_memory: Some(6144),
+            preset: Some(JvmPreset::Zgc),
+            ..InstanceJvmSettings::default()
+        };
+
+        let options = JvmOptions::resolve(&global, &instance, Some(16384));
+        assert_eq!(options.max_memory, 6144);
+        assert_eq!(options.min_memory, Some(1024));
+        assert_eq!(options.game_args, vec!["--fullscreen"]);
+        assert_eq!(
+            options.jvm_arguments(),
+            vec!["-Xms1024M", "-Xmx6144M", "-XX:+UseZGC", "-Dglobal=true", "-Dinstance=true"]
+        );
+        assert!(options.validate(Some(16384), Some(21)).is_ok());
+        assert!(options.validate(Some(16384), Some(8)).is_err());
+        assert!(options.validate(Some(4096), Some(21)).is_err());
+
+        let defaults = JvmOptions::resolve(&JvmSettings::default(), &InstanceJvmSettings::default(), Some(16384));
+        assert_eq!(defaults.jvm_arguments(), vec!["-Xmx8192M"]);
+
+        let invalid = |change: fn(&mut JvmOptions)| {
+            let mut options = defaults.clone();
+            change(&mut options);
+            options.validate(None, Some(21)).is_err()
+        };
+        assert!(invalid(|it| it.min_memory = Some(10000)));
+        assert!(invalid(|it| it.max_memory = 128));
+        assert!(invalid(|it| it.args.push("-Xmx2G".to_string())));
+        assert!(invalid(|it| it.args.push("nogui".to_string())));
+        assert!(invalid(|it| it.game_args.push(" ".to_string())));
+        assert!(invalid(|it| {
+            it.preset = JvmPreset::Aikar;
+            it.args.push("-XX:+UseShenandoahGC".to_string());
+        }));
+        assert!(!invalid(|it| it.args.push("-XX:+UseShenandoahGC".to_string())));
+    }
+}
@@ -0,0 +1,66 @@
+use std::fs::{create_dir_all, read, write};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Emits every log event as an XML record on stdout, see the file itself for details
+const LAUNCHER_LOG_CONFIG: &str = include_str!("../../log4j2-launcher.xml");
+const LAUNCHER_LOG_CONFIG_NAME: &str = "yakclient-log4j2.xml";
+
+// The version's Log4j config once it is on disk, along with the JVM argument that points at it
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    // Something like `-Dlog4j.configurationFile=${path}`
+    pub argument: String,
+    pub path: PathBuf,
+}
+
+impl LoggingConfig {
+    pub fn jvm_arguments(&self, launcher_config: Option<&Path>) -> Result<Vec<String>, String> {
+        let path = launcher_config.unwrap_or(&self.path);
+        let path = path.to_str().ok_or("The logging config's path has to be valid UTF-8")?;
+        let mut arguments = vec![self.argument.replace("${path}", path)];
+
+        // Our config can't rely on Mojang's patches against lookups in messages
+        if launcher_config.is_some() {
+            arguments.push("-Dlog4j2.formatMsgNoLookups=true".to_string());
+        }
+
+        Ok(arguments)
+    }
+}
+
+// Writes the launcher's own config next to Mojang's, leaving it alone if it's already current
+pub fn write_launcher_config(path: &Path) -> io::Result<PathBuf> {
+    let config_path = path.join("assets").join("log_configs").join(LAUNCHER_LOG_CONFIG_NAME);
+
+    if read(&config_path).ok().as_deref() != Some(LAUNCHER_LOG_CONFIG.as_bytes()) {
+        create_dir_all(config_path.parent().unwrap())?;
+        write(&config_path, LAUNCHER_LOG_CONFIG)?;
+    }
+
+    Ok(config_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logging_arguments() {
+        let config = LoggingConfig {
+            argument: "-Dlog4j.configurationFile=${path}".to_string(),
+            path: PathBuf::from("assets/log_configs/client-1.12.xml"),
+        };
+
+        assert_eq!(
+            config.jvm_arguments(None).unwrap(),
+            vec!["-Dlog4j.configurationFile=assets/log_configs/client-1.12.xml"]
+        );
+
+        let launcher_config = write_launcher_config(Path::new("tests/log-config")).unwrap();
+        let arguments = config.jvm_arguments(Some(&launcher_config)).unwrap();
+
+        assert!(arguments[0].ends_with(LAUNCHER_LOG_CONFIG_NAME));
+        assert_eq!(arguments[1], "-Dlog4j2.formatMsgNoLookups=true");
+    }
+}
@@ -1,11 +1,314 @@
+use crate::instances::read_instances;
+use crate::launch::analyzer::{analyze, format_diagnoses};
+use crate::launch::redact::Redactor;
+use crate::persist::PersistedData;
+use crate::settings::LoggingSettings;
 use crate::util::rand::generate_random_id;
 use crate::yakclient_dir;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use open::that;
-use std::fs::create_dir_all;
+use serde::Serialize;
+use std::fs::{create_dir_all, read_dir, remove_file, File};
+use std::io;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::State;
+use time::OffsetDateTime;
+
+const SESSION_LOG_EXTENSION: &str = ".log";
+const COMPRESSED_LOG_EXTENSION: &str = ".log.gz";
+
+// Searches stop once they have found this many lines
+const DEFAULT_SEARCH_RESULTS: usize = 500;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SessionLog {
+    pub instance_id: String,
+    // The file name, which is what the other commands take
+    pub name: String,
+    pub size: u64,
+    // Milliseconds since the epoch
+    pub modified: u64,
+    pub compressed: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SessionLogMatch {
+    pub instance_id: String,
+    pub name: String,
+    // Starting at 1
+    pub line_number: usize,
+    pub line: String,
+}
+
+// Every instance keeps its sessions in its own directory in here
+pub fn session_logs_dir() -> PathBuf {
+    yakclient_dir().join("logs")
+}
+
+// Creates the log for a new session of an instance, then compresses and cleans up the ones before it
+pub fn open_session_log(
+    dir: &Path,
+    instance_id: &str,
+    settings: &LoggingSettings,
+) -> io::Result<(PathBuf, File)> {
+    let dir = dir.join(instance_id);
+    create_dir_all(&dir)?;
+
+    let timestamp = file_timestamp();
+    let mut path = dir.join(format!("{}{}", timestamp, SESSION_LOG_EXTENSION));
+    let mut attempt = 1;
+    while path.exists() || compressed_path(&path).exists() {
+        path = dir.join(format!("{}-{}{}", timestamp, attempt, SESSION_LOG_EXTENSION));
+        attempt += 1;
+    }
+
+    let file = File::create(&path)?;
+    rotate_session_logs(&dir, &path, settings)?;
+
+    Ok((path, file))
+}
+
+// Sorts the same way as the time it stands for
+pub fn file_timestamp() -> String {
+    let now = OffsetDateTime::now_utc();
+
+    format!(
+        "{}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        now.year(),
+        now.month() as u8,
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second()
+    )
+}
+
+fn compressed_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".gz");
+    PathBuf::from(path)
+}
+
+// Compresses every finished session and deletes the oldest ones past the limits. The current session
+// is the newest, so it's always kept.
+pub fn rotate_session_logs(dir: &Path, current: &Path, settings: &LoggingSettings) -> io::Result<()> {
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        let is_finished = path != current
+            && path.file_name().and_then(|it| it.to_str()).is_some_and(|it| it.ends_with(SESSION_LOG_EXTENSION));
+
+        if is_finished {
+            let mut encoder = GzEncoder::new(File::create(compressed_path(&path))?, Compression::default());
+            io::copy(&mut File::open(&path)?, &mut encoder)?;
+            encoder.finish()?;
+
+            remove_file(&path)?;
+        }
+    }
+
+    let instance_id = dir.file_name().and_then(|it| it.to_str()).unwrap_or_default();
+    let mut total_size = 0;
+    for (index, log) in list_instance_logs(dir, instance_id)?.into_iter().enumerate() {
+        total_size += log.size;
+
+        if index > 0 && (index >= settings.max_sessions || total_size > settings.max_size) {
+            remove_file(dir.join(&log.name))?;
+        }
+    }
+
+    Ok(())
+}
+
+// Newest first
+fn list_instance_logs(dir: &Path, instance_id: &str) -> io::Result<Vec<SessionLog>> {
+    let mut logs = Vec::new();
+
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        let Some(name) = entry.file_name().to_str().map(|it| it.to_string()) else {
+            continue;
+        };
+
+        let compressed = name.ends_with(COMPRESSED_LOG_EXTENSION);
+        if !compressed && !name.ends_with(SESSION_LOG_EXTENSION) {
+            continue;
+        }
+
+        let metadata = entry.metadata()?;
+        logs.push(SessionLog {
+            instance_id: instance_id.to_string(),
+            name,
+            size: metadata.len(),
+            modified: metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map(|it| it.as_millis() as u64)
+                .unwrap_or_default(),
+            compressed,
+        });
+    }
+
+    // Names start with the time the session started, which is more reliable than when it was modified
+    logs.sort_by(|a, b| b.name.cmp(&a.name));
+
+    Ok(logs)
+}
+
+pub fn list_session_logs_in(dir: &Path, instance_id: Option<&str>) -> io::Result<Vec<SessionLog>> {
+    if let Some(instance_id) = instance_id {
+        let dir = dir.join(valid_name(instance_id)?);
+        return if dir.is_dir() { list_instance_logs(&dir, instance_id) } else { Ok(vec![]) };
+    }
+
+    let mut logs = Vec::new();
+    if !dir.is_dir() {
+        return Ok(logs);
+    }
+
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        if let (true, Some(instance_id)) = (entry.path().is_dir(), entry.file_name().to_str()) {
+            logs.extend(list_instance_logs(&entry.path(), instance_id)?);
+        }
+    }
+    logs.sort_by(|a, b| b.modified.cmp(&a.modified));
+
+    Ok(logs)
+}
+
+// Names come from the frontend, so they can't be allowed to point anywhere else
+fn valid_name(name: &str) -> io::Result<&str> {
+    let is_valid = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(|it| it == '/' || it == '\\');
+
+    if is_valid {
+        Ok(name)
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid log name '{}'", name)))
+    }
+}
+
+pub fn read_session_log_in(dir: &Path, instance_id: &str, name: &str) -> io::Result<String> {
+    let path = dir.join(valid_name(instance_id)?).join(valid_name(name)?);
+    let file = BufReader::new(File::open(&path)?);
+
+    let mut bytes = Vec::new();
+    if name.ends_with(COMPRESSED_LOG_EXTENSION) {
+        GzDecoder::new(file).read_to_end(&mut bytes)?;
+    } else if name.ends_with(SESSION_LOG_EXTENSION) {
+        let mut file = file;
+        file.read_to_end(&mut bytes)?;
+    } else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("'{}' isn't a session log", name)));
+    }
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub fn search_session_logs_in(
+    dir: &Path,
+    instance_id: Option<&str>,
+    query: &str,
+    max_results: usize,
+) -> io::Result<Vec<SessionLogMatch>> {
+    let query = query.to_lowercase();
+    let mut matches = Vec::new();
+
+    for log in list_session_logs_in(dir, instance_id)? {
+        let text = read_session_log_in(dir, &log.instance_id, &log.name)?;
+
+        for (index, line) in text.lines().enumerate() {
+            if matches.len() >= max_results {
+                return Ok(matches);
+            }
+
+            if line.to_lowercase().contains(&query) {
+                matches.push(SessionLogMatch {
+                    instance_id: log.instance_id.clone(),
+                    name: log.name.clone(),
+                    line_number: index + 1,
+                    line: line.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+// Every instance's sessions when no instance is given
+#[tauri::command]
+pub fn list_session_logs(instance: Option<String>) -> Result<Vec<SessionLog>, String> {
+    list_session_logs_in(&session_logs_dir(), instance.as_deref()).map_err(|e| e.to_string())
+}
+
+// Session logs are kept as the game printed them, they're only redacted on the way out
+fn redact_for_display(persisted_data: &PersistedData, text: String) -> String {
+    match Redactor::for_display(persisted_data) {
+        Some(redactor) => redactor.redact(&text),
+        None => text,
+    }
+}
+
+#[tauri::command]
+pub fn read_session_log(
+    instance: String,
+    name: String,
+    persisted_data: State<'_, PersistedData>,
+) -> Result<String, String> {
+    let text = read_session_log_in(&session_logs_dir(), &instance, &name).map_err(|e| e.to_string())?;
+
+    Ok(redact_for_display(&persisted_data, text))
+}
+
+// The last `lines` lines of a session, works while the session is still being written
+#[tauri::command]
+pub fn tail_session_log(
+    instance: String,
+    name: String,
+    lines: usize,
+    persisted_data: State<'_, PersistedData>,
+) -> Result<Vec<String>, String> {
+    let text = read_session_log_in(&session_logs_dir(), &instance, &name).map_err(|e| e.to_string())?;
+    let mut tail = text.lines().rev().take(lines).collect::<Vec<_>>();
+    tail.reverse();
+
+    let tail = redact_for_display(&persisted_data, tail.join("\n"));
+    Ok(tail.lines().map(|it| it.to_string()).collect())
+}
+
+// Case insensitive, newest sessions first
+#[tauri::command]
+pub fn search_session_logs(
+    instance: Option<String>,
+    query: String,
+    max_results: Option<usize>,
+    persisted_data: State<'_, PersistedData>,
+) -> Result<Vec<SessionLogMatch>, String> {
+    let mut matches = search_session_logs_in(
+        &session_logs_dir(),
+        instance.as_deref(),
+        &query,
+        max_results.unwrap_or(DEFAULT_SEARCH_RESULTS),
+    ).map_err(|e| e.to_string())?;
+
+    if let Some(redactor) = Redactor::for_display(&persisted_data) {
+        matches.iter_mut().for_each(|it| it.line = redactor.redact(&it.line));
+    }
+
+    Ok(matches)
+}
 
 #[tauri::command]
 pub fn export_logs(
-    logs: String
+    mut logs: String,
+    persisted_data: State<'_, PersistedData>,
 ) -> Result<(), String> {
     let log_dir = yakclient_dir().join("logs");
     let log_file = log_dir.join(format!("log-{}.txt", generate_random_id(4)));
@@ -14,6 +317,17 @@ pub fn export_logs(
         create_dir_all(&log_dir).map_err(|e| e.to_string())?;
     }
 
+    // The console doesn't say which instance the logs came from, any of their extensions could be it
+    let extensions = read_instances(&persisted_data)
+        .into_iter()
+        .flat_map(|it| it.extensions)
+        .collect::<Vec<_>>();
+    let diagnoses = analyze(&[&logs], &extensions);
+    if !diagnoses.is_empty() {
+        logs.push_str(&format_diagnoses(&diagnoses));
+    }
+    let logs = Redactor::from_persisted(&persisted_data).redact(&logs);
+
     // First line of code written inside the MIT campus... of many more
     // (lowkey a sh*tty one but its ok)
     std::fs::write(&log_file, logs).map_err(|e| e.to_string())?;
@@ -21,4 +335,46 @@ pub fn export_logs(
     that(log_file).map_err(|e| e.to_string())?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{remove_dir_all, write};
+    use std::io::Write;
+
+    #[test]
+    fn test_session_logs() {
+        let dir = Path::new("tests/session-logs");
+        if dir.exists() {
+            remove_dir_all(dir).unwrap();
+        }
+
+        let instance_dir = dir.join("default");
+        create_dir_all(&instance_dir).unwrap();
+        write(instance_dir.join("2024-01-01_10-00-00.log"), "[10:00:00] [main/INFO]: Oldest\n").unwrap();
+        write(instance_dir.join("2024-01-02_10-00-00.log"), "[10:00:00] [main/ERROR]: Crashed\n").unwrap();
+
+        let settings = LoggingSettings {
+            max_sessions: 2,
+            ..LoggingSettings::default()
+        };
+        let (path, mut file) = open_session_log(dir, "default", &settings).unwrap();
+        writeln!(file, "[10:00:00] [main/INFO]: Current").unwrap();
+
+        let logs = list_session_logs_in(dir, Some("default")).unwrap();
+        let names = logs.iter().map(|it| it.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec![
+            path.file_name().unwrap().to_str().unwrap(),
+            "2024-01-02_10-00-00.log.gz",
+        ]);
+
+        assert_eq!(read_session_log_in(dir, "default", &logs[1].name).unwrap(), "[10:00:00] [main/ERROR]: Crashed\n");
+        assert!(read_session_log_in(dir, "default", "../default/2024-01-02_10-00-00.log.gz").is_err());
+
+        let matches = search_session_logs_in(dir, None, "crashed", 10).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line_number, 1);
+        assert!(search_session_logs_in(dir, None, "oldest", 10).unwrap().is_empty());
+    }
+}
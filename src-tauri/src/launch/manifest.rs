@@ -0,0 +1,224 @@
+use crate::launch::minecraft::Error::{Serde, UnknownVersion};
+use crate::launch::minecraft::{inherits_from, Error, VersionEntry, VersionManifest, VERSION_MANIFEST};
+use bytes::Bytes;
+use crate::task::download::DownloadScheduler;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::{create_dir_all, read, read_dir, write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// How long a cached manifest is trusted before asking Mojang again
+const MANIFEST_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionListing {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub release_time: String,
+    pub installed: bool,
+    // Only set for custom versions, the ones installers like Fabric's put in `versions`
+    pub inherits_from: Option<String>,
+}
+
+pub fn manifest_path(path: &Path) -> PathBuf {
+    path.join("versions").join("version_manifest_v2.json")
+}
+
+fn read_cached(cache_path: &Path) -> Option<VersionManifest> {
+    serde_json::from_slice(&read(cache_path).ok()?).ok()
+}
+
+async fn fetch_manifest(downloads: &DownloadScheduler) -> Result<Bytes, Error> {
+    Ok(downloads.get(VERSION_MANIFEST)
+        .await?
+        .bytes()
+        .await?)
+}
+
+// Reads the cached manifest while it is within its TTL, otherwise it is refreshed. When Mojang can't
+// be reached the cached copy is used no matter how old it is.
+pub async fn version_manifest(
+    path: &Path,
+    downloads: &DownloadScheduler,
+    refresh: bool,
+) -> Result<VersionManifest, Error> {
+    let cache_path = manifest_path(path);
+
+    let is_fresh = cache_path
+        .metadata()
+        .and_then(|it| it.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < MANIFEST_TTL);
+
+    if is_fresh && !refresh {
+        if let Some(manifest) = read_cached(&cache_path) {
+            return Ok(manifest);
+        }
+    }
+
+    match fetch_manifest(downloads).await {
+        Ok(bytes) => {
+            let manifest: VersionManifest = serde_json::from_slice(&bytes).map_err(Serde)?;
+
+            if let Some(parent) = cache_path.parent() {
+                create_dir_all(parent)?;
+            }
+            write(&cache_path, &bytes)?;
+
+            Ok(manifest)
+        }
+        Err(e) => read_cached(&cache_path).ok_or(e),
+    }
+}
+
+pub async fn find_version(
+    path: &Path,
+    version: &str,
+    downloads: &DownloadScheduler,
+) -> Result<VersionEntry, Error> {
+    let find = |manifest: VersionManifest| {
+        manifest.versions.into_iter().find(|entry| entry.id == version)
+    };
+
+    if let Some(entry) = find(version_manifest(path, downloads, false).await?) {
+        return Ok(entry);
+    }
+
+    // The cached manifest might just be older than the version
+    find(version_manifest(path, downloads, true).await?)
+        .ok_or(UnknownVersion(version.to_string()))
+}
+
+pub async fn list_versions(
+    path: &Path,
+    types: Option<Vec<String>>,
+    refresh: bool,
+    downloads: &DownloadScheduler,
+) -> Result<Vec<VersionListing>, Error> {
+    let manifest = version_manifest(path, downloads, refresh).await?;
+
+    let known = manifest.versions.iter().map(|entry| entry.id.clone()).collect::<HashSet<_>>();
+    let custom = custom_versions(path, &known);
+
+    Ok(custom
+        .into_iter()
+        .chain(manifest.versions.into_iter().map(|entry| {
+            let version_path = path.join("versions").join(&entry.id);
+            let installed = version_path.join(format!("{}.json", entry.id)).exists()
+                && version_path.join(format!("{}.jar", entry.id)).exists();
+
+            VersionListing {
+                id: entry.id,
+                type_field: entry.type_field,
+                release_time: entry.release_time,
+                installed,
+                inherits_from: None,
+            }
+        }))
+        .filter(|listing| {
+            types.as_ref().map(|types| types.contains(&listing.type_field)).unwrap_or(true)
+        })
+        .collect())
+}
+
+// Versions on disk that Mojang doesn't know about, newest first
+fn custom_versions(path: &Path, known: &HashSet<String>) -> Vec<VersionListing> {
+    let Ok(entries) = read_dir(path.join("versions")) else {
+        return vec![];
+    };
+
+    let mut versions = entries
+        .filter_map(|entry| {
+            let id = entry.ok()?.file_name().to_str()?.to_string();
+            if known.contains(&id) {
+                return None;
+            }
+
+            let json = read(path.join("versions").join(&id).join(format!("{}.json", id))).ok()?;
+            let json: Value = serde_json::from_slice(&json).ok()?;
+            let field = |name: &str| json.get(name).and_then(Value::as_str).map(str::to_string);
+
+            Some(VersionListing {
+                type_field: field("type").unwrap_or("release".to_string()),
+                release_time: field("releaseTime").unwrap_or_default(),
+                installed: true,
+                inherits_from: inherits_from(&json),
+                id,
+            })
+        })
+        .collect::<Vec<_>>();
+    versions.sort_by(|a, b| b.release_time.cmp(&a.release_time));
+
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::DownloadSettings;
+
+    #[tokio::test]
+    async fn test_cached_manifest() {
+        let path = PathBuf::from("tests/manifest");
+        create_dir_all(path.join("versions")).unwrap();
+        write(
+            manifest_path(&path),
+            r#"{
+                "latest": { "release": "1.21.1", "snapshot": "24w33a" },
+                "versions": [
+                    {
+                        "id": "1.21.1",
+                        "type": "release",
+                        "url": "https://piston-meta.mojang.com/v1/packages/1.21.1.json",
+                        "time": "2024-08-08T12:24:45+00:00",
+                        "releaseTime": "2024-08-08T12:24:45+00:00",
+                        "sha1": "0000000000000000000000000000000000000000",
+                        "complianceLevel": 1
+                    },
+                    {
+                        "id": "b1.7.3",
+                        "type": "old_beta",
+                        "url": "https://piston-meta.mojang.com/v1/packages/b1.7.3.json",
+                        "time": "2011-07-08T00:00:00+00:00",
+                        "releaseTime": "2011-07-08T00:00:00+00:00",
+                        "sha1": "0000000000000000000000000000000000000000",
+                        "complianceLevel": 0
+                    }
+                ]
+            }"#,
+        ).unwrap();
+
+        let custom_path = path.join("versions").join("fabric-loader-0.16.5-1.21.1");
+        create_dir_all(&custom_path).unwrap();
+        write(
+            custom_path.join("fabric-loader-0.16.5-1.21.1.json"),
+            r#"{
+                "id": "fabric-loader-0.16.5-1.21.1",
+                "inheritsFrom": "1.21.1",
+                "releaseTime": "2024-09-09T14:13:42+0000",
+                "type": "release",
+                "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient"
+            }"#,
+        ).unwrap();
+
+        let downloads = DownloadScheduler::new(&DownloadSettings::default());
+        let versions = list_versions(&path, Some(vec!["old_beta".to_string()]), false, &downloads)
+            .await
+            .unwrap();
+
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].id, "b1.7.3");
+        assert!(!versions[0].installed);
+
+        let versions = list_versions(&path, None, false, &downloads).await.unwrap();
+
+        assert_eq!(versions[0].id, "fabric-loader-0.16.5-1.21.1");
+        assert_eq!(versions[0].inherits_from.as_deref(), Some("1.21.1"));
+        assert!(versions[0].installed);
+    }
+}
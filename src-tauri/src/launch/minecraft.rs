@@ -1,17 +1,20 @@
 use crate::launch::minecraft::Error::{
-    InvalidInfo, Network, Serde, UnknownVersion, ZipExtract, IO,
+    ChecksumMismatch, Download, InvalidInfo, Network, Serde, UnknownVersion, Zip, IO,
 };
-use crate::task::copy::copy_stream_tracking;
-use crate::task::{Progress, Task, TaskManager};
-use crate::util::{map_async, Compress};
+use crate::launch::log_config::LoggingConfig;
+use crate::launch::natives::{extract_natives, NativeArtifact};
+use crate::launch::rules::RuleContext;
+use crate::task::download::{part_path, DownloadFailures, DownloadRequest, DownloadScheduler};
+use crate::task::{Task, TaskManager};
+use crate::util::hash::sha1_bytes;
 use bytes::Bytes;
 use discord_rich_presence::new_client;
-use futures::future::join_all;
 use futures::stream::{FuturesUnordered, StreamExt};
 use futures::{FutureExt, TryFutureExt};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use serde_urlencoded::{from_bytes, from_reader};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::{OsStr, OsString};
 use std::fmt::{format, Display, Formatter};
 use std::fs::{create_dir, File};
@@ -19,14 +22,11 @@ use std::future::Future;
 use std::io::{copy, Cursor};
 use std::ops::DerefMut;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::sync::Arc;
-use reqwest::Client;
 use tokio::fs::create_dir_all;
 use tokio::io::{self, AsyncWriteExt};
 use uuid::serde::urn::deserialize;
-use zip_extract::{extract, ZipExtractError};
 use crate::launch::lib_patch::{fetch_library_patches, patch_library};
+use crate::launch::manifest::{find_version, version_manifest};
 
 #[derive(Debug)]
 pub enum Error {
@@ -35,7 +35,13 @@ pub enum Error {
     UnknownVersion(String),
     IO(io::Error),
     InvalidInfo(&'static str),
-    ZipExtract(ZipExtractError),
+    Zip(zip::result::ZipError),
+    ChecksumMismatch {
+        artifact: String,
+        expected: String,
+        actual: String,
+    },
+    Download(DownloadFailures),
 }
 
 impl From<io::Error> for Error {
@@ -50,6 +56,12 @@ impl From<reqwest::Error> for Error {
     }
 }
 
+impl From<DownloadFailures> for Error {
+    fn from(value: DownloadFailures) -> Self {
+        Download(value)
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let str = match self {
@@ -58,7 +70,12 @@ impl Display for Error {
             UnknownVersion(e) => e.to_string(),
             IO(e) => e.to_string(),
             InvalidInfo(e) => e.to_string(),
-            ZipExtract(e) => e.to_string(),
+            Zip(e) => e.to_string(),
+            ChecksumMismatch { artifact, expected, actual } => format!(
+                "Checksum mismatch for {} (expected {}, got {})",
+                artifact, expected, actual
+            ),
+            Download(e) => e.to_string(),
         };
 
         write!(f, "{}", str)
@@ -71,29 +88,23 @@ pub struct MinecraftEnvironment {
     pub libraries: Vec<PathBuf>,
     pub asset_path: PathBuf,
     pub asset_index_name: String,
+    // Where old versions read assets from by name, the regular asset path for everything else
+    pub game_assets: PathBuf,
     pub natives_path: PathBuf,
+    // Only versions using Log4j have one
+    pub logging: Option<LoggingConfig>,
     pub arguments: Arguments,
     pub main_class: String,
     pub java_version: JavaVersion,
+    pub version_type: String,
 }
 
 pub trait FormatForCommand {
     fn format(
         &self,
         values: &HashMap<&str, String>,
+        context: &RuleContext,
     ) -> Vec<String>;
-
-    fn apply(
-        &self,
-        command: &mut Command,
-        values: &HashMap<&str, String>,
-    ) {
-        let format = self.format(values);
-
-        for arg in format {
-            command.arg(arg);
-        }
-    }
 }
 
 fn replace_option_variable(
@@ -122,16 +133,20 @@ fn replace_option_variable(
 
 // An argument chunk where if not all are substitutable none are returned
 impl FormatForCommand for Vec<&[Argument]> {
-    fn format(&self, values: &HashMap<&str, String>) -> Vec<String> {
+    fn format(&self, values: &HashMap<&str, String>, context: &RuleContext) -> Vec<String> {
         self.iter().flat_map(|it| {
             it.iter().map(|arg| {
-                format_arg(&values, arg)
+                format_arg(&values, arg, context)
             }).collect::<Option<Vec<Vec<String>>>>().unwrap_or(vec![])
         }).flatten().collect()
     }
 }
 
-fn format_arg(values: &HashMap<&str, String>, arg: &Argument) -> Option<Vec<String>> {
+fn format_arg(
+    values: &HashMap<&str, String>,
+    arg: &Argument,
+    context: &RuleContext,
+) -> Option<Vec<String>> {
     match arg {
         Argument::Value(s) => {
             match s {
@@ -149,16 +164,7 @@ fn format_arg(values: &HashMap<&str, String>, arg: &Argument) -> Option<Vec<Stri
         Argument::ArgumentWithRules {
             rules, value
         } => {
-            let os_rule = MinecraftEnvironment::current_os();
-
-            let apply = rules.iter().all(|it| {
-                MinecraftEnvironment::apply_rule(
-                    &os_rule,
-                    it,
-                )
-            });
-
-            if apply {
+            if context.allows(rules) {
                 match value {
                     ValueType::String(str) => {
                         replace_option_variable(str, &values).map(|t| vec![t])
@@ -170,16 +176,17 @@ fn format_arg(values: &HashMap<&str, String>, arg: &Argument) -> Option<Vec<Stri
                     }
                 }
             } else {
-                None
+                // Not a failed substitution, so whatever it is paired with still goes through
+                Some(vec![])
             }
         }
     }
 }
 
 impl FormatForCommand for Vec<Argument> {
-    fn format(&self, values: &HashMap<&str, String>) -> Vec<String> {
+    fn format(&self, values: &HashMap<&str, String>, context: &RuleContext) -> Vec<String> {
         self.iter().flat_map(|arg| {
-            let arg = format_arg(values, arg);
+            let arg = format_arg(values, arg, context);
 
             arg.unwrap_or(vec![])
         }).collect()
@@ -187,431 +194,476 @@ impl FormatForCommand for Vec<Argument> {
 }
 
 const MINECRAFT_RESOURCES: &'static str = "https://resources.download.minecraft.net";
+const MINECRAFT_LIBRARIES: &'static str = "https://libraries.minecraft.net";
 
-impl MinecraftEnvironment {
-    async fn download_version_info(version: &str, client: &Client, path: &PathBuf) -> Result<(), Error> {
-        let response = client.get(VERSION_MANIFEST).send().await.map_err(Network)?;
-        let bytes = response.bytes().await.map_err(Network)?;
-        let bytes = bytes.as_ref();
-        let manifest: VersionManifest = serde_json::from_slice(bytes).map_err(Serde)?;
-
-        let entry = manifest
-            .versions
-            .iter()
-            .find(|v| v.id == version)
-            .ok_or(UnknownVersion(version.to_string()))?;
+// A single file the environment depends on, along with where it comes from.
+#[derive(Serialize, Debug, Clone)]
+pub struct Artifact {
+    pub name: String,
+    pub url: String,
+    pub path: PathBuf,
+    // Unknown for version JSONs, the manifest doesn't include it
+    pub size: Option<u64>,
+    // Unknown for some libraries from third-party installers
+    pub sha1: Option<String>,
+}
+
+impl Artifact {
+    fn from_download(name: String, info: &DownloadInfo, path: PathBuf) -> Artifact {
+        Artifact {
+            name,
+            url: info.url.clone(),
+            path,
+            size: info.size,
+            sha1: info.sha1.clone(),
+        }
+    }
 
-        let url = entry.url;
+    pub fn is_valid(&self) -> io::Result<bool> {
+        self.request().is_complete()
+    }
 
-        let response = client.get(url).send().await.map_err(Network)?;
-        let bytes = response.bytes().await.map_err(Network)?;
+    // Reads the whole artifact into memory, retrying once if the hash doesn't match. Only meant for
+    // the small JSON files.
+    pub async fn fetch_bytes(&self, downloads: &DownloadScheduler) -> Result<Bytes, Error> {
+        let mut actual = String::new();
 
-        copy(&mut Cursor::new(bytes), &mut File::create(path)?)?;
+        for _ in 0..2 {
+            let bytes = downloads.get(&self.url).await?.bytes().await?;
 
-        Ok(())
+            actual = sha1_bytes(&bytes);
+            if self.sha1.as_ref().map_or(true, |expected| actual == *expected) {
+                return Ok(bytes);
+            }
+        }
+
+        Err(self.mismatch(actual))
     }
 
-    fn current_os() -> OsRule {
-        let name = if cfg!(target_os = "windows") {
-            Some("windows".to_string())
-        } else if cfg!(target_os = "macos") {
-            Some("osx".to_string())
-        } else if cfg!(target_os = "linux") {
-            Some("linux".to_string())
-        } else if cfg!(target_os = "freebsd") {
-            Some("freebsd".to_string())
-        } else if cfg!(target_os = "dragonfly") {
-            Some("dragonfly".to_string())
-        } else if cfg!(target_os = "openbsd") {
-            Some("openbsd".to_string())
-        } else if cfg!(target_os = "netbsd") {
-            Some("netbsd".to_string())
-        } else if cfg!(target_os = "android") {
-            Some("android".to_string())
-        } else {
-            None
-        };
+    pub async fn fetch(&self, downloads: &DownloadScheduler) -> Result<(), Error> {
+        let bytes = self.fetch_bytes(downloads).await?;
 
-        let arch = if cfg!(target_arch = "x86_64") {
-            Some("x86_64".to_string())
-        } else if cfg!(target_arch = "x86") {
-            Some("x86".to_string())
-        } else if cfg!(target_arch = "arm") {
-            Some("arm".to_string())
-        } else if cfg!(target_arch = "aarch64") {
-            Some("arm64".to_string())
-        } else if cfg!(target_arch = "mips") {
-            Some("mips".to_string())
-        } else if cfg!(target_arch = "mips64") {
-            Some("mips64".to_string())
-        } else {
-            None // Unsupported or unknown architecture
-        };
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent).await?;
+        }
+        // Written aside first so an interrupted write never looks like a cached copy
+        let part_path = part_path(&self.path);
+        copy(&mut Cursor::new(bytes), &mut File::create(&part_path)?)?;
+        std::fs::rename(&part_path, &self.path)?;
 
-        OsRule { name, arch }
+        Ok(())
     }
 
-    fn apply_rules<'a>(os: &OsRule, libraries: &'a [Library]) -> Vec<&'a Library> {
-        libraries
-            .iter()
-            .filter(|lib| Self::filter_library(os, lib))
-            .collect()
+    pub fn request(&self) -> DownloadRequest {
+        DownloadRequest {
+            name: self.name.clone(),
+            url: self.url.clone(),
+            path: self.path.clone(),
+            size: self.size,
+            sha1: self.sha1.clone(),
+        }
     }
 
-    fn apply_rule(os: &OsRule, rule: &Rule) -> bool {
-        if rule.action == "allow" {
-            match &rule.os {
-                Some(os_rule) => {
-                    (os_rule.name == os.name)
-                        && (os_rule.arch.is_none() || os_rule.arch == os.arch)
-                }
-                None => true,
-            }
-        } else {
-            false
+    fn mismatch(&self, actual: String) -> Error {
+        ChecksumMismatch {
+            artifact: self.name.clone(),
+            expected: self.sha1.clone().unwrap_or_default(),
+            actual,
         }
     }
+}
 
-    fn filter_library(os: &OsRule, lib: &Library) -> bool {
-        if let Some(rules) = &lib.rules {
-            rules.iter().any(|rule| {
-                Self::apply_rule(os, rule)
-            })
-        } else {
-            true
+// Turns a maven coordinate (group:artifact:version) into its path inside a repository
+fn maven_path(name: &str, classifier: Option<&str>) -> Option<String> {
+    let (name, extension) = name.split_once('@').unwrap_or((name, "jar"));
+    let mut parts = name.split(':');
+
+    let group = parts.next()?;
+    let artifact = parts.next()?;
+    let version = parts.next()?;
+    let classifier = classifier.or(parts.next());
+
+    let file_name = match classifier {
+        Some(classifier) => format!("{}-{}-{}.{}", artifact, version, classifier, extension),
+        None => format!("{}-{}.{}", artifact, version, extension),
+    };
+
+    Some(format!(
+        "{}/{}/{}/{}",
+        group.replace('.', "/"),
+        artifact,
+        version,
+        file_name
+    ))
+}
+
+impl MinecraftEnvironment {
+    pub fn version_json_path(path: &Path, version: &str) -> PathBuf {
+        path.join("versions").join(version).join(format!("{}.json", version))
+    }
+
+    // Looks the version up in the manifest to find where its JSON comes from
+    pub async fn version_json_artifact(
+        path: &Path,
+        version: &str,
+        downloads: &DownloadScheduler,
+    ) -> Result<Artifact, Error> {
+        let entry = find_version(path, version, downloads).await?;
+
+        Ok(Self::version_json_entry_artifact(path, version, entry))
+    }
+
+    // Only looks in the manifest as it is, custom versions aren't in it and there may be no manifest
+    // at all when offline. Either way the JSON on disk is all there is to go by.
+    pub async fn listed_version_json_artifact(
+        path: &Path,
+        version: &str,
+        downloads: &DownloadScheduler,
+    ) -> Option<Artifact> {
+        let manifest = version_manifest(path, downloads, false).await.ok()?;
+        let entry = manifest.versions.into_iter().find(|entry| entry.id == version)?;
+
+        Some(Self::version_json_entry_artifact(path, version, entry))
+    }
+
+    fn version_json_entry_artifact(path: &Path, version: &str, entry: VersionEntry) -> Artifact {
+        Artifact {
+            name: format!("{}.json", version),
+            url: entry.url,
+            path: Self::version_json_path(path, version),
+            size: None,
+            sha1: Some(entry.sha1),
         }
     }
 
-    pub async fn environment(
-        path: PathBuf,
+    // Reads the version's JSON along with every JSON it inherits from, fetching any that are missing or
+    // don't match the manifest
+    pub async fn resolve_version_info(
+        path: &Path,
         version: &str,
-        tasks: &mut TaskManager,
-    ) -> Result<MinecraftEnvironment, Error> {
-        let version_path = path.join("versions").join(version);
-        if !version_path.exists() {
-            create_dir_all(&version_path).await?;
+        downloads: &DownloadScheduler,
+    ) -> Result<VersionInfo, Error> {
+        let mut chain = Vec::new();
+        let mut current = Some(version.to_string());
+
+        while let Some(version) = current {
+            if chain.len() >= MAX_INHERITANCE_DEPTH {
+                return Err(InvalidInfo("Version inherits from too many others"));
+            }
+
+            let json_path = Self::version_json_path(path, &version);
+            let artifact = if json_path.exists() {
+                Self::listed_version_json_artifact(path, &version, downloads).await
+            } else {
+                Some(Self::version_json_artifact(path, &version, downloads).await?)
+            };
+
+            // A JSON cut short by an interrupted download would otherwise fail to parse on every launch
+            if let Some(artifact) = artifact {
+                if !artifact.is_valid()? {
+                    artifact.fetch(downloads).await?;
+                }
+            }
+
+            let json: Value = serde_json::from_slice(&std::fs::read(&json_path)?).map_err(Serde)?;
+            current = inherits_from(&json);
+            chain.push(json);
         }
 
-        let client = Client::new();
+        Self::merge_version_info(chain)
+    }
+
+    // Folds a version and its ancestors, child first, into one standalone version. Children add
+    // libraries and arguments on top of their parent's and replace everything else.
+    pub fn merge_version_info(chain: Vec<Value>) -> Result<VersionInfo, Error> {
+        let merged = Self::merge_version_chain(chain)?;
+
+        Self::read_version_info(&serde_json::to_vec(&merged).map_err(Serde)?)
+    }
+
+    pub fn merge_version_chain(chain: Vec<Value>) -> Result<Value, Error> {
+        chain
+            .into_iter()
+            .rev()
+            .reduce(merge_version_json)
+            .ok_or(InvalidInfo("No version JSON to read"))
+    }
+
+    // Like `resolve_version_info` but only ever reads what is already on disk
+    pub fn installed_version_chain(path: &Path, version: &str) -> Result<Vec<Value>, Error> {
+        let mut chain = Vec::new();
+        let mut current = Some(version.to_string());
+
+        while let Some(version) = current {
+            if chain.len() >= MAX_INHERITANCE_DEPTH {
+                return Err(InvalidInfo("Version inherits from too many others"));
+            }
 
-        let client_json_path = version_path.join(format!("{}.json", version));
-        if !client_json_path.exists() {
-            Self::download_version_info(version, &client, &client_json_path).await?;
+            let json = std::fs::read(Self::version_json_path(path, &version))?;
+            let json: Value = serde_json::from_slice(&json).map_err(Serde)?;
+            current = inherits_from(&json);
+            chain.push(json);
         }
 
-        let mut info: VersionInfo =
-            serde_json::from_reader(File::open(&client_json_path)?).map_err(Serde)?;
+        Ok(chain)
+    }
+
+    pub fn read_version_info(bytes: &[u8]) -> Result<VersionInfo, Error> {
+        let mut info: VersionInfo = serde_json::from_slice(bytes).map_err(Serde)?;
 
         // Thank you so much Modrinth! You actually saved me like weeks
         let patches = fetch_library_patches()?;
         info.libraries = info.libraries.into_iter().flat_map(|lib| {
             patch_library(&patches, lib)
         }).collect();
-        println!("{}", serde_json::to_string(&info.libraries).unwrap());
 
-        let client_info = (&info.downloads.get("client"))
+        Ok(info)
+    }
+
+    pub fn client_artifact(path: &Path, info: &VersionInfo) -> Result<Artifact, Error> {
+        let client_info = info.downloads.get("client")
             .ok_or(InvalidInfo("No client available to download"))?;
+        let jar = info.jar.as_ref().unwrap_or(&info.id);
 
-        let client_path = version_path.join(format!("{}.jar", &info.id));
+        Ok(Artifact::from_download(
+            format!("{}.jar", jar),
+            client_info,
+            path.join("versions").join(jar).join(format!("{}.jar", jar)),
+        ))
+    }
 
-        let client_jar_fut = if !client_path.exists() {
-            let mut client_response = client.get(&client_info.url).send()
-                .await
-                .map_err(Network)?
-                .bytes_stream();
-            let client_size = client_info.size;
+    pub fn library_artifacts(path: &Path, info: &VersionInfo) -> Vec<Artifact> {
+        let context = RuleContext::current(Features::default());
 
-            let fut = tasks.submit("Download Minecraft", |mut task| async move {
-                let result: Result<(), Error> = copy_stream_tracking(
-                    &mut client_response,
-                    &mut File::create(client_path).map_err(IO)?,
-                    client_size,
-                    &mut task.progress,
-                )
-                    .await;
+        info.libraries
+            .iter()
+            .filter(|library| context.allows(library.rules.as_deref().unwrap_or_default()))
+            .filter_map(|library| {
+                let Some(download) = library.downloads.artifact.as_ref() else {
+                    return Self::maven_library_artifact(path, library);
+                };
+                let library_path = download.path.clone()
+                    .or_else(|| maven_path(&library.name, None))?;
+
+                Some(Artifact::from_download(
+                    library.name.clone(),
+                    download,
+                    path.join("libraries").join(library_path),
+                ))
+            })
+            .collect()
+    }
 
-                result?;
+    // Libraries without any downloads are resolved against their repository, Mojang's unless they say
+    // otherwise. Ones with only natives have nothing to put on the classpath.
+    fn maven_library_artifact(path: &Path, library: &Library) -> Option<Artifact> {
+        if library.downloads.classifiers.is_some() || library.natives.is_some() {
+            return None;
+        }
 
-                task.progress.update(1.0).await;
+        let library_path = maven_path(&library.name, None)?;
+        let repository = library.url.as_deref().unwrap_or(MINECRAFT_LIBRARIES);
 
-                Ok::<(), Error>(())
-            });
+        Some(Artifact {
+            name: library.name.clone(),
+            url: format!("{}/{}", repository.trim_end_matches('/'), library_path),
+            path: path.join("libraries").join(library_path),
+            size: library.size,
+            sha1: library.sha1.clone(),
+        })
+    }
 
-            Some(fut)
-        } else {
-            None
-        };
+    pub fn native_artifacts(path: &Path, info: &VersionInfo) -> Vec<NativeArtifact> {
+        let context = RuleContext::current(Features::default());
 
-        enum LibraryProcessRequest {
-            DownloadArtifact(DownloadInfo),
-            NativeExtract(DownloadInfo),
-        }
+        info.libraries
+            .iter()
+            .filter(|library| context.allows(library.rules.as_deref().unwrap_or_default()))
+            .filter_map(|library| {
+                let natives = library.natives.as_ref()?;
+                let os_name = context.os_name.as_ref()?;
+
+                // Simply just to account for the poor design of library-patches. I would like to redo this
+                // eventually as there is no reason it should be done like this.
+                let classifier = context.os_arch.as_ref()
+                    .and_then(|arch| natives.get(&format!("{}-{}", os_name, arch)))
+                    .or_else(|| natives.get(os_name))?
+                    .replace("${arch}", if cfg!(target_pointer_width = "64") { "64" } else { "32" });
+
+                let download = library.downloads.classifiers.as_ref()?.get(&classifier)?;
+                let native_path = download.path.clone()
+                    .or_else(|| maven_path(&library.name, Some(&classifier)))?;
+
+                Some(NativeArtifact {
+                    artifact: Artifact::from_download(
+                        format!("{}:{}", library.name, classifier),
+                        download,
+                        path.join("libraries").join(native_path),
+                    ),
+                    exclude: library.extract
+                        .as_ref()
+                        .map(|extract| extract.exclude.clone())
+                        .unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
 
-        impl LibraryProcessRequest {
-            async fn do_action(
-                self,
-                path: &PathBuf,
-                client: &Client,
-                mut tracker: Progress,
-            ) -> Result<Option<PathBuf>, Error> {
-                match self {
-                    LibraryProcessRequest::DownloadArtifact(info) => {
-                        let path = path
-                            .join("libraries")
-                            .join(&info.path.expect("Path should be included on library"));
-
-                        if path.exists() {
-                            return Ok(Some(path));
-                        }
-
-                        create_dir_all(path.parent().unwrap()).await?;
-
-                        let mut response = client.get(&info.url).send()
-                            .await
-                            .map_err(Network)?
-                            .bytes_stream();
-
-                        let size = info.size;
-
-                        let result: Result<(), Error> = copy_stream_tracking(
-                            &mut response,
-                            &mut File::create(&path).map_err(IO)?,
-                            size,
-                            &mut tracker,
-                        )
-                            .await;
-
-                        result?;
-
-                        tracker.update(1.0).await;
-
-                        Ok(Some(path))
-                    }
-                    LibraryProcessRequest::NativeExtract(info) => {
-                        let path = path.join("bin");
-
-                        create_dir_all(&path).await?;
-
-                        let response = map_async(
-                            client.get(&info.url).send().await,
-                            |r| r.bytes(),
-                        ).await.compress();
-
-                        match response {
-                            Ok(bytes) => {
-                                extract(Cursor::new(bytes), Path::new(&path), false)
-                                    .map_err(ZipExtract)?;
-
-                                tracker.update(1.0).await;
-
-                                Ok(None)
-                            }
-                            Err(e) => {
-                                tracker.erroneously_complete(&e).await;
-                                Ok(None)
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    pub fn logging_artifact(path: &Path, info: &VersionInfo) -> Option<Artifact> {
+        let file = &info.logging.as_ref()?.client.file;
+
+        Some(Artifact {
+            name: file.id.clone(),
+            url: file.url.clone(),
+            path: path.join("assets").join("log_configs").join(&file.id),
+            size: Some(file.size),
+            sha1: Some(file.sha1.clone()),
+        })
+    }
 
-        let bin_path = path.join("bin");
-        if bin_path.exists() {
-            std::fs::remove_dir_all(bin_path)?;
+    pub fn asset_index_artifact(path: &Path, info: &VersionInfo) -> Artifact {
+        Artifact {
+            name: format!("{}.json", info.asset_index.id),
+            url: info.asset_index.url.clone(),
+            path: path.join("assets").join("indexes").join(format!("{}.json", info.assets)),
+            size: Some(info.asset_index.size),
+            sha1: Some(info.asset_index.sha1.clone()),
         }
-        let (client_libraries, libraries_task) = tasks.submit("Download Minecraft libraries", |task: Task| {
-            let arc = task.to_arc();
-
-            let libraries = info.libraries.clone();
-            let iter = libraries
-                .into_iter()
-                .flat_map(|library| {
-                    let mut vec = Vec::<(LibraryProcessRequest, u64)>::new();
-
-                    if let Some(ref lib) = library.downloads.artifact {
-                        if Self::filter_library(&Self::current_os(), &library) {
-                            vec.push((
-                                LibraryProcessRequest::DownloadArtifact(lib.clone()),
-                                lib.size,
-                            ))
-                        }
-                    }
+    }
 
-                    if let Some(natives) = library.natives {
-                        let os = Self::current_os();
-
-                        // Simply just to account for the poor design of library-patches. I would like to redo this
-                        // eventually as there is no reason it should be done like this.
-                        let classifier = natives.get(
-                            &format!("{}-{}", &os.name.clone().unwrap(), &os.arch.unwrap())
-                        )
-                            .or_else(|| {
-                                natives.get(&os.name.unwrap())
-                            });
-
-                        if let Some(classifier) = classifier {
-                            if let Some(classifiers) = library.downloads.classifiers {
-                                if let Some(classifier_download) = classifiers.get(classifier) {
-                                    vec.push((
-                                        LibraryProcessRequest::NativeExtract(
-                                            classifier_download.clone(),
-                                        ),
-                                        classifier_download.size,
-                                    ));
-                                }
-                            }
-                        }
-                    }
+    pub fn asset_artifacts(path: &Path, index: &AssetObjects) -> Vec<Artifact> {
+        let objects_path = path.join("assets").join("objects");
 
-                    vec
-                });
+        index.objects
+            .iter()
+            .map(|(name, object)| {
+                let prefix = &object.hash[0..2];
+
+                Artifact {
+                    name: name.clone(),
+                    url: format!("{}/{}/{}", MINECRAFT_RESOURCES, prefix, object.hash),
+                    path: objects_path.join(prefix).join(&object.hash),
+                    size: Some(object.size),
+                    sha1: Some(object.hash.clone()),
+                }
+            })
+            .collect()
+    }
 
-            let vec = iter.collect::<Vec<(LibraryProcessRequest, u64)>>();
-            let total_size = vec.iter().map(|(_, size)| *size as f64).sum::<f64>();
-            let iter = vec.into_iter();
+    pub async fn environment(
+        path: PathBuf,
+        game_dir: &Path,
+        version: &str,
+        tasks: &mut TaskManager,
+        downloads: &DownloadScheduler,
+    ) -> Result<MinecraftEnvironment, Error> {
+        let info = Self::resolve_version_info(&path, version, downloads).await?;
 
-            let futures = iter.map(|(request, size)| {
-                request.do_action(&path, &client, Task::child(&arc, (size as f64) / total_size))
+        let client_jar = Self::client_artifact(&path, &info)?;
+
+        let client_jar_fut = if !client_jar.is_valid()? {
+            let request = client_jar.request();
+
+            let fut = tasks.submit("Download Minecraft", |mut task| async move {
+                downloads.download(&request, &mut task.progress).await
             });
 
-            (join_all(futures), arc)
+            Some(fut)
+        } else {
+            None
+        };
+
+        let libraries = Self::library_artifacts(&path, &info);
+        let natives = Self::native_artifacts(&path, &info);
+
+        let libraries_fut = tasks.submit("Download Minecraft libraries", |task: Task| {
+            let requests = libraries
+                .iter()
+                .chain(natives.iter().map(|native| &native.artifact))
+                // Installers put these in place themselves
+                .filter(|artifact| !artifact.url.is_empty())
+                .map(Artifact::request)
+                .collect();
+
+            downloads.download_all(requests, task.to_arc())
         });
 
-        let assets_path = path.join("assets");
-        let indexes_path = assets_path.join("indexes");
-        if !indexes_path.exists() {
-            create_dir_all(&indexes_path).await?;
-        }
-        let objects_path = assets_path.join("objects");
-        if !objects_path.exists() {
-            create_dir_all(&objects_path).await?;
+        let asset_index_artifact = Self::asset_index_artifact(&path, &info);
+        if !asset_index_artifact.is_valid()? {
+            asset_index_artifact.fetch(downloads).await?;
         }
 
-        let indexes_json_path = indexes_path.join(format!("{}.json", info.assets));
-
-        if !indexes_json_path.exists() {
-            let asset_index = reqwest::get(&info.asset_index.url).await?.bytes().await?;
-            copy(
-                &mut Cursor::new(asset_index),
-                &mut File::create(&indexes_json_path)?,
-            )?;
+        let logging_artifact = Self::logging_artifact(&path, &info);
+        if let Some(artifact) = &logging_artifact {
+            if !artifact.is_valid()? {
+                artifact.fetch(downloads).await?;
+            }
         }
+        let logging = info.logging.as_ref().zip(logging_artifact).map(|(logging, artifact)| {
+            LoggingConfig {
+                argument: logging.client.argument.clone(),
+                path: artifact.path,
+            }
+        });
 
         let asset_index: AssetObjects =
-            serde_json::from_reader(File::open(&indexes_json_path)?).map_err(Serde)?;
-
-        let (asset_fut, assets_task) = tasks.submit("Download Minecraft Assets", |task: Task| {
-            let objects = &asset_index.objects;
-
-            let task = Task::to_arc(task);
-
-            let borrowable_task = Arc::clone(&task);
+            serde_json::from_reader(File::open(&asset_index_artifact.path)?).map_err(Serde)?;
 
-            let total_size = objects
-                .iter()
-                .map(|o| o.1.size).sum::<u64>();
+        let assets = Self::asset_artifacts(&path, &asset_index);
 
-            let iter = objects
+        let assets_fut = tasks.submit("Download Minecraft Assets", |task: Task| {
+            let requests = assets
                 .iter()
-                .map(move |entry| {
-                    let checksum = &entry.1.hash;
-
-                    let parent_path = (&objects_path).join(&checksum[0..2].to_string());
-                    let path = parent_path.join(&checksum);
-
-                    (path, entry)
+                // Hashing every object on each launch is too slow, so existing objects only get a size
+                // check here. Anything that is downloaded is hashed.
+                .filter(|artifact| {
+                    artifact.path.metadata().map(|it| Some(it.len()) != artifact.size).unwrap_or(true)
                 })
-                .filter(|entry| !entry.0.exists())
-                .map(|entry| {
-                    let checksum = &entry.1.1.hash;
-                    let size = &entry.1.1.size;
-
-                    let path = entry.0;
-
-                    let mut tracker =
-                        Task::child(&borrowable_task, (*size as f64) / (total_size as f64));
-
-                    let client = Arc::new(&client);
-
-                    async move {
-                        let mut tries = 0;
-                        create_dir_all(&path.parent().unwrap()).await?;
-
-                        let client = Arc::clone(&client);
-
-                        for _ in 0..2 {
-                            let mut asset_response = client.get(
-                                format!(
-                                    "{}/{}/{}",
-                                    MINECRAFT_RESOURCES,
-                                    checksum[0..2].to_string(),
-                                    checksum.to_string()
-                                ).as_str(),
-                            ).send();
-
-                            tries = tries + 1;
-                            let r = async {
-                                let mut asset_response = asset_response
-                                    .await?
-                                    .bytes_stream();
-
-
-                                let r: Result<(), Error> = copy_stream_tracking(
-                                    &mut asset_response,
-                                    &mut File::create(&path).map_err(IO)?,
-                                    size.clone(),
-                                    &mut tracker,
-                                )
-                                    .await;
-                                r?;
-
-                                Ok::<(), Error>(())
-                            }.await;
-
-                            if r.is_ok() || (r.is_err() && tries >= 3) {
-                                return r;
-                            }
-                        }
-                        Ok(())
-                    }
-                });
+                .map(Artifact::request)
+                .collect();
 
-            (join_all(iter), task)
+            downloads.download_all(requests, task.to_arc())
         });
 
-        if let Some(fut) = client_jar_fut {
-            fut.await?;
-        }
+        let client_jar_fut = async {
+            match client_jar_fut {
+                Some(fut) => fut.await.map_err(DownloadFailures::from),
+                None => Ok(()),
+            }
+        };
 
-        let library_paths = client_libraries
-            .await
-            .into_iter()
-            .collect::<Result<Vec<Option<PathBuf>>, Error>>()?
+        let (client_jar_result, libraries_result, assets_result) =
+            futures::join!(client_jar_fut, libraries_fut, assets_fut);
+
+        let failures = [client_jar_result, libraries_result, assets_result]
             .into_iter()
-            .filter_map(|x| x)
+            .filter_map(Result::err)
+            .flat_map(|it| it.0)
             .collect::<Vec<_>>();
-        libraries_task.lock().await.update(1.0).await;
 
-        asset_fut
-            .await
-            .into_iter()
-            .collect::<Result<Vec<()>, Error>>()?;
-        assets_task.lock().await.update(1.0).await;
+        if !failures.is_empty() {
+            return Err(Download(DownloadFailures(failures)));
+        }
+
+        let natives_path = extract_natives(&path, version, &natives)?;
+
+        let game_assets = Self::layout_legacy_assets(&path, game_dir, &info.assets, &asset_index)?;
 
         Result::<MinecraftEnvironment, Error>::Ok(MinecraftEnvironment {
-            client_jar: version_path.join(format!("{}.jar", &info.id)),
-            libraries: library_paths,
-            asset_path: assets_path,
+            client_jar: client_jar.path.clone(),
+            libraries: libraries.into_iter().map(|artifact| artifact.path).collect(),
+            asset_path: path.join("assets"),
             asset_index_name: info.asset_index.id,
-            natives_path: path.join("bin"),
+            game_assets,
+            natives_path,
+            logging,
             arguments: info.arguments.clone().unwrap_or_else(|| {
                 let default_jvm_args = vec![ // This is an option we always want even if MC doesnt say it needs it
                                              Argument::Value(ValueType::String("-Djava.library.path=${natives_directory}".to_string())),
                                              Argument::Value(ValueType::Array(vec!["-cp".to_string(), "${classpath}".to_string()]))
                 ];
                 if let Some(args) = info.minecraft_arguments {
-                    let args = args.split(" ")
+                    let args = args.split_whitespace()
                         .map(|str| {
                             Argument::Value(ValueType::String(str.to_string()))
                         })
@@ -630,8 +682,125 @@ impl MinecraftEnvironment {
             }),
             main_class: info.main_class.clone(),
             java_version: info.java_version,
+            version_type: info.type_field,
         })
     }
+
+    // Copies objects out of the hashed store to where old versions look for them by name: a
+    // virtual directory for `virtual` indexes, the game directory's `resources` for
+    // `map_to_resources` ones. Returns the directory `${game_assets}` should point at.
+    pub fn layout_legacy_assets(
+        path: &Path,
+        game_dir: &Path,
+        assets_id: &str,
+        index: &AssetObjects,
+    ) -> Result<PathBuf, Error> {
+        let target = if index.map_to_resources {
+            game_dir.join("resources")
+        } else if index.is_virtual {
+            path.join("assets").join("virtual").join(assets_id)
+        } else {
+            return Ok(path.join("assets"));
+        };
+
+        let objects_path = path.join("assets").join("objects");
+        for (name, object) in &index.objects {
+            let destination = target.join(name);
+            let is_current = destination
+                .metadata()
+                .is_ok_and(|metadata| metadata.len() == object.size);
+
+            if !is_current {
+                if let Some(parent) = destination.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::copy(
+                    objects_path.join(&object.hash[0..2]).join(&object.hash),
+                    &destination,
+                )?;
+            }
+        }
+
+        Ok(target)
+    }
+}
+
+// INHERITANCE
+
+pub const MAX_INHERITANCE_DEPTH: usize = 8;
+
+pub fn inherits_from(json: &Value) -> Option<String> {
+    json.get("inheritsFrom")?.as_str().map(str::to_string)
+}
+
+// Libraries are the same if everything but their version matches
+fn library_key(library: &Value) -> Option<String> {
+    let name = library.get("name")?.as_str()?;
+    let parts = name.split(':').collect::<Vec<_>>();
+
+    Some(match parts.as_slice() {
+        [group, artifact, _version, rest @ ..] => format!("{}:{}:{}", group, artifact, rest.join(":")),
+        _ => name.to_string(),
+    })
+}
+
+fn merge_version_json(parent: Value, child: Value) -> Value {
+    let (mut merged, child) = match (parent, child) {
+        (Value::Object(parent), Value::Object(child)) => (parent, child),
+        (_, child) => return child,
+    };
+
+    // The client jar stays the one the root version downloads
+    if !child.contains_key("jar") {
+        if let Some(jar) = merged.get("jar").or(merged.get("id")).cloned() {
+            merged.insert("jar".to_string(), jar);
+        }
+    }
+
+    for (key, value) in child {
+        match key.as_str() {
+            "inheritsFrom" => {}
+            "libraries" => {
+                let child_libraries = value.as_array().cloned().unwrap_or_default();
+                let overridden = child_libraries.iter().filter_map(library_key).collect::<HashSet<_>>();
+
+                let parent_libraries = merged
+                    .remove("libraries")
+                    .and_then(|it| it.as_array().cloned())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|library| {
+                        library_key(library).map_or(true, |key| !overridden.contains(&key))
+                    });
+
+                let libraries = child_libraries.into_iter().chain(parent_libraries).collect();
+                merged.insert(key, Value::Array(libraries));
+            }
+            "arguments" => {
+                let mut arguments = merged
+                    .remove("arguments")
+                    .and_then(|it| it.as_object().cloned())
+                    .unwrap_or_default();
+
+                for (kind, child_arguments) in value.as_object().cloned().unwrap_or_default() {
+                    let mut combined = arguments
+                        .remove(&kind)
+                        .and_then(|it| it.as_array().cloned())
+                        .unwrap_or_default();
+                    combined.extend(child_arguments.as_array().cloned().unwrap_or_default());
+
+                    arguments.insert(kind, Value::Array(combined));
+                }
+
+                merged.insert(key, Value::Object(arguments));
+            }
+            _ => {
+                merged.insert(key, value);
+            }
+        }
+    }
+
+    Value::Object(merged)
 }
 
 // VERSION MANIFEST
@@ -639,38 +808,54 @@ impl MinecraftEnvironment {
 pub const VERSION_MANIFEST: &'static str =
     "https://launchermeta.mojang.com/mc/game/version_manifest_v2.json";
 
-#[derive(Deserialize)]
-pub struct VersionManifest<'a> {
-    #[serde(borrow)]
-    pub versions: Vec<VersionEntry<'a>>,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VersionManifest {
+    pub latest: LatestVersions,
+    pub versions: Vec<VersionEntry>,
 }
 
-#[derive(Deserialize)]
-pub struct VersionEntry<'a> {
-    pub id: &'a str,
-    pub url: &'a str,
-    pub sha1: &'a str,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LatestVersions {
+    pub release: String,
+    pub snapshot: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VersionEntry {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub url: String,
+    pub time: String,
+    #[serde(rename = "releaseTime")]
+    pub release_time: String,
+    pub sha1: String,
+    #[serde(rename = "complianceLevel")]
+    pub compliance_level: Option<i32>,
 }
 
 // VERSION INFO
 
 #[derive(Serialize, Deserialize, Debug)]
-struct VersionInfo {
+pub struct VersionInfo {
     arguments: Option<Arguments>,
     #[serde(rename = "assetIndex")]
     asset_index: AssetIndex,
     assets: String,
-    #[serde(rename = "complianceLevel")]
+    // Old versions are missing the next few, so they fall back to what those versions expect
+    #[serde(rename = "complianceLevel", default)]
     compliance_level: i32,
     downloads: HashMap<String, DownloadInfo>,
     id: String,
-    #[serde(rename = "javaVersion")]
-    java_version: JavaVersion,
-    libraries: Vec<Library>,
-    logging: Logging,
+    // The version whose client jar is used, set when resolving inheritsFrom
+    jar: Option<String>,
+    #[serde(rename = "javaVersion", default = "JavaVersion::legacy")]
+    pub java_version: JavaVersion,
+    pub libraries: Vec<Library>,
+    logging: Option<Logging>,
     #[serde(rename = "mainClass")]
     main_class: String,
-    #[serde(rename = "minimumLauncherVersion")]
+    #[serde(rename = "minimumLauncherVersion", default)]
     minimum_launcher_version: i32,
     #[serde(rename = "releaseTime")]
     release_time: String,
@@ -714,22 +899,25 @@ pub struct Rule {
 pub struct OsRule {
     pub name: Option<String>,
     pub arch: Option<String>,
+    // A regex matched against `os.version`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Features {
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Features {
     #[serde(rename = "is_demo_user")]
-    is_demo_user: Option<bool>,
+    pub is_demo_user: Option<bool>,
     #[serde(rename = "has_custom_resolution")]
-    has_custom_resolution: Option<bool>,
+    pub has_custom_resolution: Option<bool>,
     #[serde(rename = "has_quick_plays_support")]
-    has_quick_plays_support: Option<bool>,
+    pub has_quick_plays_support: Option<bool>,
     #[serde(rename = "is_quick_play_singleplayer")]
-    is_quick_play_singleplayer: Option<bool>,
+    pub is_quick_play_singleplayer: Option<bool>,
     #[serde(rename = "is_quick_play_multiplayer")]
-    is_quick_play_multiplayer: Option<bool>,
+    pub is_quick_play_multiplayer: Option<bool>,
     #[serde(rename = "is_quick_play_realms")]
-    is_quick_play_realms: Option<bool>,
+    pub is_quick_play_realms: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -744,8 +932,9 @@ struct AssetIndex {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct DownloadInfo {
-    sha1: String,
-    size: u64,
+    sha1: Option<String>,
+    size: Option<u64>,
+    // Empty for files installers generate locally rather than download
     url: String,
     path: Option<String>,
 }
@@ -757,10 +946,28 @@ pub struct JavaVersion {
     pub major_version: i32,
 }
 
+impl JavaVersion {
+    // Versions from before Mojang bundled runtimes all run on Java 8
+    pub fn legacy() -> JavaVersion {
+        JavaVersion {
+            component: "jre-legacy".to_string(),
+            major_version: 8,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Library {
+    #[serde(default)]
     pub downloads: LibraryDownloads,
     pub name: String,
+    // Installers like Fabric's only give a maven repository to resolve the name against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rules: Option<Vec<Rule>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -774,7 +981,7 @@ pub struct LibraryExtract {
     pub exclude: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct LibraryDownloads {
     pub artifact: Option<DownloadInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -804,12 +1011,17 @@ struct LoggingFile {
 
 // ASSET INDEX
 #[derive(Deserialize, Debug)]
-struct AssetObjects {
+pub struct AssetObjects {
     pub objects: HashMap<String, AssetContent>,
+    // Pre-1.7 indexes, where the game reads assets by name instead of by hash
+    #[serde(rename = "virtual", default)]
+    pub is_virtual: bool,
+    #[serde(default)]
+    pub map_to_resources: bool,
 }
 
 #[derive(Deserialize, Debug)]
-struct AssetContent {
+pub struct AssetContent {
     hash: String,
     size: u64,
 }
@@ -817,6 +1029,7 @@ struct AssetContent {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::settings::DownloadSettings;
     use crate::task::tests::PrintingTrackerBuilder;
     use crate::task::TrackerBuilder;
     use tokio::fs::create_dir_all;
@@ -829,13 +1042,51 @@ mod tests {
 
         let mc_buf = PathBuf::from("tests/mc");
         create_dir_all(&mc_buf).await.unwrap();
-        let env = MinecraftEnvironment::environment(mc_buf, "1.8.9", &mut tasks)
+        let downloads = DownloadScheduler::new(&DownloadSettings::default());
+        let env = MinecraftEnvironment::environment(mc_buf.clone(), &mc_buf, "1.8.9", &mut tasks, &downloads)
             .await
             .unwrap();
 
         println!("{:#?}", env);
     }
 
+    #[tokio::test]
+    async fn test_listed_version_json() {
+        let mc_buf = PathBuf::from("tests/listed_version_json");
+        let _ = std::fs::remove_dir_all(&mc_buf);
+        let json = br#"{"id": "test-1"}"#;
+
+        let manifest = serde_json::json!({
+            "latest": {"release": "test-1", "snapshot": "test-1"},
+            "versions": [{
+                "id": "test-1",
+                "type": "release",
+                "url": "https://piston-meta.mojang.com/test-1.json",
+                "time": "",
+                "releaseTime": "",
+                "sha1": sha1_bytes(json),
+            }]
+        });
+        std::fs::create_dir_all(mc_buf.join("versions/test-1")).unwrap();
+        std::fs::write(crate::launch::manifest::manifest_path(&mc_buf), manifest.to_string()).unwrap();
+
+        let downloads = DownloadScheduler::new(&DownloadSettings::default());
+        let artifact = MinecraftEnvironment::listed_version_json_artifact(&mc_buf, "test-1", &downloads)
+            .await
+            .unwrap();
+
+        // Cut short by an interrupted download
+        std::fs::write(&artifact.path, &json[..8]).unwrap();
+        assert!(!artifact.is_valid().unwrap());
+        std::fs::write(&artifact.path, json).unwrap();
+        assert!(artifact.is_valid().unwrap());
+
+        // Custom versions are left to whatever is on disk
+        assert!(MinecraftEnvironment::listed_version_json_artifact(&mc_buf, "fabric-loader-1", &downloads)
+            .await
+            .is_none());
+    }
+
     #[tokio::test]
     async fn test_apply_args() {
         let args = vec![
@@ -851,7 +1102,8 @@ mod tests {
 
 
         let result = args.format(
-            &values
+            &values,
+            &RuleContext::current(Features::default()),
         );
 
         println!("{:#?}", result);
@@ -860,4 +1112,127 @@ mod tests {
         assert_eq!(result.get(2).unwrap(), "--test3=First test");
         assert_eq!(result.get(1).unwrap(), "--test2=Second test");
     }
+
+    #[test]
+    fn test_legacy_version_info() {
+        // Trimmed from Mojang's b1.7.3.json, which has no javaVersion, complianceLevel or logging
+        let info: VersionInfo = serde_json::from_str(r#"{
+            "assetIndex": {
+                "id": "pre-1.6",
+                "sha1": "3d8e55480977e32acd9844e545177e69a52f594b",
+                "size": 74091,
+                "totalSize": 49505710,
+                "url": "https://piston-meta.mojang.com/v1/packages/3d8e55480977e32acd9844e545177e69a52f594b/pre-1.6.json"
+            },
+            "assets": "pre-1.6",
+            "downloads": {
+                "client": {
+                    "sha1": "43db9b498cb67058d2e12d394e6507722e71bb45",
+                    "size": 1465375,
+                    "url": "https://launcher.mojang.com/v1/objects/43db9b498cb67058d2e12d394e6507722e71bb45/client.jar"
+                }
+            },
+            "id": "b1.7.3",
+            "libraries": [],
+            "mainClass": "net.minecraft.launchwrapper.Launch",
+            "minecraftArguments": "${auth_player_name} ${auth_session} --gameDir ${game_directory} --assetsDir ${game_assets}",
+            "minimumLauncherVersion": 7,
+            "releaseTime": "2011-07-07T22:00:00+00:00",
+            "time": "2011-07-07T22:00:00+00:00",
+            "type": "old_beta"
+        }"#).unwrap();
+
+        assert_eq!(info.java_version.major_version, 8);
+        assert!(info.logging.is_none());
+        assert!(info.arguments.is_none());
+    }
+
+    #[test]
+    fn test_inherited_version_info() {
+        let parent = serde_json::json!({
+            "arguments": { "game": ["--version", "${version_name}"], "jvm": ["-cp", "${classpath}"] },
+            "assetIndex": { "id": "17", "sha1": "", "size": 0, "totalSize": 0, "url": "" },
+            "assets": "17",
+            "downloads": { "client": { "sha1": "", "size": 0, "url": "" } },
+            "id": "1.21.1",
+            "javaVersion": { "component": "java-runtime-delta", "majorVersion": 21 },
+            "libraries": [
+                { "name": "org.ow2.asm:asm:9.3", "downloads": { "artifact": { "path": "org/ow2/asm/asm/9.3/asm-9.3.jar", "sha1": "", "size": 0, "url": "" } } },
+                { "name": "com.mojang:brigadier:1.3.10", "downloads": { "artifact": { "path": "com/mojang/brigadier/1.3.10/brigadier-1.3.10.jar", "sha1": "", "size": 0, "url": "" } } }
+            ],
+            "mainClass": "net.minecraft.client.main.Main",
+            "releaseTime": "2024-08-08T12:24:45+00:00",
+            "time": "2024-08-08T12:24:45+00:00",
+            "type": "release"
+        });
+        // Trimmed from what Fabric's installer writes
+        let child = serde_json::json!({
+            "arguments": { "game": [], "jvm": ["-DFabricMcEmu= net.minecraft.client.main.Main "] },
+            "id": "fabric-loader-0.16.5-1.21.1",
+            "inheritsFrom": "1.21.1",
+            "libraries": [
+                { "name": "org.ow2.asm:asm:9.7.1", "url": "https://maven.fabricmc.net/", "sha1": "f0ed132a49244b042cd0e15702ab9f2ce3cc8436", "size": 126093 }
+            ],
+            "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+            "releaseTime": "2024-09-09T14:13:42+0000",
+            "time": "2024-09-09T14:13:42+0000",
+            "type": "release"
+        });
+
+        let info = MinecraftEnvironment::merge_version_info(vec![child, parent]).unwrap();
+
+        assert_eq!(info.id, "fabric-loader-0.16.5-1.21.1");
+        assert_eq!(info.jar.as_deref(), Some("1.21.1"));
+        assert_eq!(info.main_class, "net.fabricmc.loader.impl.launch.knot.KnotClient");
+        assert_eq!(info.java_version.major_version, 21);
+        assert_eq!(info.arguments.as_ref().unwrap().jvm.len(), 3);
+
+        let libraries = MinecraftEnvironment::library_artifacts(Path::new("mc"), &info);
+        let names = libraries.iter().map(|it| it.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["org.ow2.asm:asm:9.7.1", "com.mojang:brigadier:1.3.10"]);
+        assert_eq!(
+            libraries[0].url,
+            "https://maven.fabricmc.net/org/ow2/asm/asm/9.7.1/asm-9.7.1.jar"
+        );
+
+        let client = MinecraftEnvironment::client_artifact(Path::new("mc"), &info).unwrap();
+        assert_eq!(client.path, Path::new("mc/versions/1.21.1/1.21.1.jar"));
+    }
+
+    #[test]
+    fn test_legacy_asset_layout() {
+        let path = PathBuf::from("tests/legacy-assets");
+        let hash = "a9993e364706816aba3e25717850c26c9cd0d89d";
+        let object_path = path.join("assets").join("objects").join(&hash[0..2]);
+        std::fs::create_dir_all(&object_path).unwrap();
+        std::fs::write(object_path.join(hash), b"abc").unwrap();
+
+        let index: AssetObjects = serde_json::from_str(&format!(
+            r#"{{ "map_to_resources": true, "objects": {{ "sound/step/grass1.ogg": {{ "hash": "{}", "size": 3 }} }} }}"#,
+            hash
+        )).unwrap();
+
+        let game_dir = path.join("instance");
+        let game_assets = MinecraftEnvironment::layout_legacy_assets(&path, &game_dir, "pre-1.6", &index).unwrap();
+
+        assert_eq!(game_assets, game_dir.join("resources"));
+        assert_eq!(std::fs::read(game_assets.join("sound/step/grass1.ogg")).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn test_maven_path() {
+        assert_eq!(
+            maven_path("org.lwjgl:lwjgl-tinyfd:3.2.2", None).unwrap(),
+            "org/lwjgl/lwjgl-tinyfd/3.2.2/lwjgl-tinyfd-3.2.2.jar"
+        );
+        assert_eq!(
+            maven_path("org.lwjgl:lwjgl-tinyfd:3.2.2", Some("natives-linux")).unwrap(),
+            "org/lwjgl/lwjgl-tinyfd/3.2.2/lwjgl-tinyfd-3.2.2-natives-linux.jar"
+        );
+        assert_eq!(
+            maven_path("com.example:thing:1.0:sources@zip", None).unwrap(),
+            "com/example/thing/1.0/thing-1.0-sources.zip"
+        );
+        assert!(maven_path("invalid", None).is_none());
+    }
 }
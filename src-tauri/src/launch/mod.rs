@@ -1,34 +1,66 @@
+use crate::instances::{find_instance, read_instances, Instance, InstanceError};
 use crate::launch::client::{get_client, get_client_version};
+use crate::launch::analyzer::analyze;
+use crate::launch::crash::find_crashes;
+use crate::launch::gc::{GarbageReport, GarbageRoots};
+use crate::launch::hooks::LaunchHooks;
 use crate::launch::java::JreSetupError;
+use crate::launch::manifest::{list_versions, VersionListing};
 use crate::launch::minecraft::MinecraftEnvironment;
-use crate::launch::process::{capture_child, launch_process, ProcessStdoutEvent};
-use crate::launch::ClientError::{ClientNotRunning, ClientProcessError, IoError, MinecraftSetupErr, ModExtError, NetworkError, Unauthenticated};
+use crate::launch::bundle::{write_diagnostic_bundle, BundleSources};
+use crate::launch::logs::{file_timestamp, open_session_log, session_logs_dir};
+use crate::launch::options::LaunchOptions;
+use crate::launch::redact::Redactor;
+use crate::launch::output::{LogBatch, OutputTail};
+use crate::launch::process::{build_launch_command, capture_child, focus_window, launch_process, wait_for_exit, ProcessExitEvent, LaunchCommand};
+use crate::launch::verify::{repair_installation, verify_installation, InstallationReport};
+use crate::launch::ClientError::{ClientNotRunning, ClientProcessError, InstanceErr, IoError, MinecraftSetupErr, ModExtError, NetworkError, Unauthenticated};
 use crate::mods::{get_mod_extension, ModExtGenerationError};
 use crate::persist::PersistedData;
-use crate::state::{Extension, LaunchInstance, MinecraftAuthentication, Mod};
+use crate::state::{Extension, LaunchInstance, MinecraftAuthentication, ProcessInfo, ProcessRegistry};
+use crate::task::download::{DownloadFailure, DownloadScheduler};
 use crate::task::TaskManager;
-use crate::{launcher_status, minecraft_dir, yakclient_dir};
+use crate::{extframework_dir, launcher_status, minecraft_dir, yakclient_dir};
 use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
 use futures::TryFutureExt;
 use serde::{Serialize, Serializer};
 use std::fmt::{Display, Formatter};
-use std::fs::create_dir_all;
+use std::fs::{create_dir_all, read_to_string};
 use std::io;
 use std::io::{Error, Read, Write};
-use std::ops::{Deref, DerefMut};
+use std::ops::DerefMut;
+use std::path::Path;
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tauri::ipc::Channel;
-use tauri::{Emitter, State};
+use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::io::AsyncReadExt;
 use tokio::sync::Mutex;
+use uuid::Uuid;
 use crate::settings::UserSettings;
 
+pub mod analyzer;
+mod bundle;
 mod client;
+mod crash;
+mod gc;
+pub mod hooks;
 mod java;
+pub mod jvm;
 mod minecraft;
 mod process;
 mod lib_patch;
+mod log_config;
 pub mod logs;
+mod manifest;
+mod natives;
+pub mod options;
+pub mod output;
+pub mod redact;
+mod rules;
+mod verify;
 
 #[derive(Debug)]
 pub enum ClientError {
@@ -40,7 +72,11 @@ pub enum ClientError {
     ClientAlreadyRunning,
     JreInstallError(JreSetupError),
     ModExtError(ModExtGenerationError),
-    MinecraftSetupErr(minecraft::Error)
+    MinecraftSetupErr(minecraft::Error),
+    DownloadError(DownloadFailure),
+    InvalidLaunchOptions(String),
+    InstanceErr(InstanceError),
+    HookFailed(String),
 }
 
 impl From<Error> for ClientError {
@@ -77,43 +113,189 @@ impl Display for ClientError {
             ClientError::JreInstallError(t) => t.to_string(),
             ClientError::ModExtError(t) => t.to_string(),
             MinecraftSetupErr(t) => {t.to_string()}
+            ClientError::DownloadError(t) => t.to_string(),
+            ClientError::InvalidLaunchOptions(t) => t.clone(),
+            InstanceErr(t) => t.to_string(),
+            ClientError::HookFailed(t) => t.clone(),
         };
         write!(f, "{}", str)
     }
 }
 
+// Starts the instance and returns the id of its process
 #[tauri::command]
 pub async fn launch_minecraft(
-    version: String,
-    process: State<'_, Arc<Mutex<Option<LaunchInstance>>>>,
+    instance: String,
+    options: Option<LaunchOptions>,
+    app: AppHandle,
+    processes: State<'_, ProcessRegistry>,
     persisted_data: State<'_, PersistedData>,
-    console_channel: Channel<ProcessStdoutEvent>,
+    console_channel: Channel<LogBatch>,
     discord_client: State<'_, std::sync::Mutex<Option<DiscordIpcClient>>>,
-    tasks: State<'_, Mutex<TaskManager>>
-) -> Result<(), ClientError> {
-    if process.lock().await.is_some() {
+    tasks: State<'_, Mutex<TaskManager>>,
+    downloads: State<'_, DownloadScheduler>,
+) -> Result<String, ClientError> {
+    let options = options.unwrap_or_default();
+    options.validate().map_err(ClientError::InvalidLaunchOptions)?;
+
+    let instance = find_instance(&persisted_data, Some(&instance)).map_err(InstanceErr)?;
+    // Held until the process is registered, so the same instance can't be started twice at once
+    let mut tasks = tasks.lock().await;
+
+    // Two games in the same directory would fight over its worlds and options
+    if processes.is_instance_running(&instance.id).await {
         return Err(ClientError::ClientAlreadyRunning);
     }
 
-    let yakclient_dir = yakclient_dir();
+    let (command, _) = prepare_launch(
+        &instance,
+        &options,
+        &persisted_data,
+        &mut *tasks,
+        &downloads,
+    ).await?;
 
-    let mut tasks = tasks.lock().await;
+    println!("Launching Minecraft");
+    let child = launch_process(&command).await?;
+    let pid = child.id();
+
+    // Opened once the game is running, so a launch stopped by its pre-launch hook leaves no empty
+    // log behind. The game still starts if its output can't be saved.
+    let settings: UserSettings = persisted_data.read_value("settings").unwrap();
+    let log = match open_session_log(&session_logs_dir(), &instance.id, &settings.logging) {
+        Ok((_, file)) => Some(file),
+        Err(e) => {
+            println!("Failed to create the session log: {}", e);
+            None
+        }
+    };
+
+    let redactor = Arc::new(Redactor::from_persisted(&persisted_data));
+    let display_redactor = Redactor::for_display(&persisted_data).map(Arc::new);
+    let (child, output) = capture_child(child, console_channel, log, display_redactor);
+
+    let process = LaunchInstance {
+        id: Uuid::new_v4().to_string(),
+        instance_id: instance.id.clone(),
+        version: instance.version.clone(),
+        pid,
+        started_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|it| it.as_millis() as u64)
+            .unwrap_or_default(),
+        child: child.clone(),
+        stop_requested: Arc::new(AtomicBool::new(false)),
+    };
+    let id = process.id.clone();
+    let stop_requested = process.stop_requested.clone();
+
+    watch_exit(app, process.info(), instance.clone(), command.hooks, child, stop_requested, output, redactor);
+    processes.insert(process).await;
+
+    if let Some(ref mut discord_client) = discord_client.lock().unwrap().deref_mut() {
+        let _ = discord_client.set_activity(
+            activity::Activity::new()
+                .state("Playing Minecraft")
+                .details(format!("Extframework {}", instance.version).as_str()),
+        );
+    };
+
+    Ok(id)
+}
+
+// Reaps the process once it exits, whether it was stopped or closed on its own, and puts everything
+// back the way it was before it started
+fn watch_exit(
+    app: AppHandle,
+    process: ProcessInfo,
+    instance: Instance,
+    hooks: LaunchHooks,
+    child: Arc<Mutex<Child>>,
+    stop_requested: Arc<AtomicBool>,
+    output: OutputTail,
+    redactor: Arc<Redactor>,
+) {
+    let started = Instant::now();
+    let started_at = SystemTime::now();
+
+    tauri::async_runtime::spawn(async move {
+        // The process still has to be unlisted and the exit reported, just without a status
+        let status = match wait_for_exit(&child).await {
+            Ok(status) => Some(status),
+            Err(e) => {
+                println!("Failed to wait on process {}: {}", process.id, e);
+                None
+            }
+        };
+
+        let mut event = ProcessExitEvent::new(
+            process.id.clone(),
+            process.instance_id,
+            status,
+            started.elapsed().as_millis() as u64,
+        );
+        event.stopped = stop_requested.load(Ordering::SeqCst);
+        // Being killed isn't a crash, whatever the output ends with
+        if !status.is_some_and(|it| it.success()) && !event.stopped {
+            event.crashes = find_crashes(&instance.game_dir, started_at, &instance.extensions);
+
+            let mut texts = vec![output.text()];
+            texts.extend(event.crashes.iter().filter_map(|it| read_to_string(&it.path).ok()));
+            let texts = texts.iter().map(String::as_str).collect::<Vec<_>>();
+            event.diagnoses = analyze(&texts, &instance.extensions);
+
+            // Analyzed before redacting, rules can depend on paths and names
+            event.crashes.iter_mut().for_each(|it| redactor.redact_crash(it));
+            event.diagnoses.iter_mut().for_each(|it| redactor.redact_diagnosis(it));
+        }
+        println!("Process {} exited: {:?}", process.id, event);
+
+        let processes = app.state::<ProcessRegistry>();
+        processes.remove(&process.id).await;
+
+        if processes.is_empty().await {
+            let discord_client = app.state::<std::sync::Mutex<Option<DiscordIpcClient>>>();
+            if let Some(ref mut discord_client) = discord_client.lock().unwrap().deref_mut() {
+                let _ = launcher_status(discord_client);
+            };
+        }
 
-    let client_path = get_client(get_client_version().await?, &mut *tasks)
+        let exit_code = event.code.map(|it| it.to_string()).unwrap_or_default();
+        let _ = app.emit("process-exit", event);
+
+        // Run once the instance is no longer listed as running, so a slow hook doesn't hold up
+        // relaunching it
+        if let Some(hook) = &hooks.post_exit {
+            match hooks.run(hook, &instance.game_dir, &[("YAKCLIENT_EXIT_CODE", exit_code)]).await {
+                Ok(output) if !output.success() => println!("{}", output.describe("post-exit")),
+                Ok(_) => {}
+                Err(e) => println!("Failed to run the post-exit hook: {}", e),
+            }
+        }
+    });
+}
+
+// Everything up to spawning the process, shared by launching and dry runs
+async fn prepare_launch(
+    instance: &Instance,
+    options: &LaunchOptions,
+    persisted_data: &PersistedData,
+    tasks: &mut TaskManager,
+    downloads: &DownloadScheduler,
+) -> Result<(LaunchCommand, Option<MinecraftAuthentication>), ClientError> {
+    let yakclient_dir = yakclient_dir();
+
+    let client_path = get_client(get_client_version().await?, tasks, downloads)
         .await?;
 
-    println!("Launching Minecraft");
     let ms_auth: Option<MinecraftAuthentication> = persisted_data.read_value("ms_auth");
 
-    let mut extensions: Vec<Extension> = persisted_data
-        .read_value("extensions")
-        .unwrap_or(Vec::new());
+    let mut extensions: Vec<Extension> = instance.extensions.clone();
     let java_dir = yakclient_dir.join("runtime");
     create_dir_all(&java_dir).map_err(IoError)?;
 
-    let mods: Vec<Mod> = persisted_data.read_value("mods").unwrap_or(Vec::new());
-    if !mods.is_empty() {
-        let mod_ext = get_mod_extension(&mods, yakclient_dir.join("repo"))
+    if !instance.mods.is_empty() {
+        let mod_ext = get_mod_extension(&instance.mods, yakclient_dir.join("repo"), downloads)
             .await
             .map_err(|e| ModExtError(e))?;
 
@@ -122,58 +304,222 @@ pub async fn launch_minecraft(
 
     println!("{:?}", extensions);
 
+    create_dir_all(&instance.game_dir).map_err(IoError)?;
+
     let env = MinecraftEnvironment::environment(
         minecraft_dir(),
-        version.as_str(),
-        &mut *tasks,
+        &instance.game_dir,
+        instance.version.as_str(),
+        tasks,
+        downloads,
     ).await.map_err(MinecraftSetupErr)?;
 
-    let child = launch_process(
-        version.clone(),
+    let settings: UserSettings = persisted_data.read_value("settings").unwrap();
+    let command = build_launch_command(
+        instance,
         java_dir,
         client_path,
         &ms_auth,
         &extensions,
         &env,
-        persisted_data.read_value::<UserSettings, &str>("settings").unwrap().debugger
+        options,
+        settings.debugger,
+        settings.logging,
+        settings.jvm,
+        tasks,
+        downloads,
     ).await?;
 
-    let child = capture_child(child, console_channel);
+    Ok((command, ms_auth))
+}
 
-    let instance = LaunchInstance { child };
+// Resolves the launch without starting the game. The access token is redacted from what's returned,
+// a script written to `script_path` reads it from `MC_ACCESS_TOKEN` instead.
+#[tauri::command]
+pub async fn dry_run_launch(
+    instance: String,
+    options: Option<LaunchOptions>,
+    script_path: Option<String>,
+    persisted_data: State<'_, PersistedData>,
+    tasks: State<'_, Mutex<TaskManager>>,
+    downloads: State<'_, DownloadScheduler>,
+) -> Result<LaunchCommand, ClientError> {
+    let options = options.unwrap_or_default();
+    options.validate().map_err(ClientError::InvalidLaunchOptions)?;
 
-    *process.lock().await = Some(instance);
+    let instance = find_instance(&persisted_data, Some(&instance)).map_err(InstanceErr)?;
+    let mut tasks = tasks.lock().await;
 
-    if let Some(ref mut discord_client) = discord_client.lock().unwrap().deref_mut() {
-        let _ = discord_client.set_activity(
-            activity::Activity::new()
-                .state("Playing Minecraft")
-                .details(format!("Extframework {}", version).as_str()),
-        );
-    };
+    let (mut command, ms_auth) = prepare_launch(
+        &instance,
+        &options,
+        &persisted_data,
+        &mut *tasks,
+        &downloads,
+    ).await?;
+
+    let access_token = ms_auth.as_ref().map(|it| it.access_token.as_str());
+
+    if let Some(script_path) = script_path {
+        write_launch_script(Path::new(&script_path), &command.script(access_token))?;
+    }
+
+    if let Some(access_token) = access_token {
+        command.redact(access_token);
+    }
+
+    Ok(command)
+}
+
+fn write_launch_script(path: &Path, script: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    std::fs::write(path, script)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    }
 
     Ok(())
 }
 
 #[tauri::command]
 pub async fn end_launch_process(
-    process: State<'_, Arc<Mutex<Option<LaunchInstance>>>>,
-    discord_client: State<'_, std::sync::Mutex<Option<DiscordIpcClient>>>,
+    id: String,
+    processes: State<'_, ProcessRegistry>,
 ) -> Result<(), ClientError> {
-    let mut guard = process.lock().await;
+    let process = processes.remove(&id).await.ok_or(ClientNotRunning)?;
+
+    // The exit watcher takes care of the rest
+    process.shutdown().await.map_err(IoError)
+}
+
+#[tauri::command]
+pub async fn list_processes(
+    processes: State<'_, ProcessRegistry>,
+) -> Result<Vec<ProcessInfo>, ClientError> {
+    Ok(processes.list().await)
+}
+
+#[tauri::command]
+pub async fn focus_process(
+    id: String,
+    processes: State<'_, ProcessRegistry>,
+) -> Result<(), ClientError> {
+    let process = processes.get(&id).await.ok_or(ClientNotRunning)?;
+
+    focus_window(process.pid).map_err(IoError)
+}
+
+#[tauri::command]
+pub async fn verify_minecraft_installation(
+    version: String,
+    repair: bool,
+    tasks: State<'_, Mutex<TaskManager>>,
+    downloads: State<'_, DownloadScheduler>,
+) -> Result<InstallationReport, ClientError> {
+    let mut report = verify_installation(&minecraft_dir(), version.as_str(), &downloads)
+        .await
+        .map_err(MinecraftSetupErr)?;
+
+    if repair && !report.is_healthy() {
+        let mut tasks = tasks.lock().await;
 
-    println!("PROCESS IS: {}", guard.is_some());
-    if let Some(process) = guard.deref() {
-        process.shutdown().await
-    } else {
-        return Err(ClientNotRunning);
+        repair_installation(&minecraft_dir(), &report, &mut *tasks, &downloads)
+            .await
+            .map_err(MinecraftSetupErr)?;
+
+        report.repaired = true;
     }
 
-    *guard = None;
+    Ok(report)
+}
 
-    if let Some(ref mut discord_client) = discord_client.lock().unwrap().deref_mut() {
-        let _ = launcher_status(discord_client);
+#[tauri::command]
+pub async fn get_minecraft_versions(
+    types: Option<Vec<String>>,
+    refresh: Option<bool>,
+    downloads: State<'_, DownloadScheduler>,
+) -> Result<Vec<VersionListing>, ClientError> {
+    list_versions(&minecraft_dir(), types, refresh.unwrap_or(false), &downloads)
+        .await
+        .map_err(MinecraftSetupErr)
+}
+
+// Reports what nothing installed uses anymore, and removes it when `delete` is set
+#[tauri::command]
+pub async fn collect_garbage(
+    delete: bool,
+    processes: State<'_, ProcessRegistry>,
+    persisted_data: State<'_, PersistedData>,
+    tasks: State<'_, Mutex<TaskManager>>,
+) -> Result<GarbageReport, ClientError> {
+    // Launches hold this until their process is registered, so nothing they are still downloading
+    // (or have just downloaded) can be taken for garbage
+    let _tasks = tasks.lock().await;
+
+    // Whatever is running may still need files nothing installed refers to
+    if delete && !processes.is_empty().await {
+        return Err(ClientError::ClientAlreadyRunning);
+    }
+
+    let mod_sets = read_instances(&persisted_data)
+        .into_iter()
+        .map(|it| it.mods)
+        .collect::<Vec<_>>();
+    let client_version = get_client_version().await?;
+
+    let roots = GarbageRoots {
+        minecraft_dir: &minecraft_dir(),
+        runtime_dir: &yakclient_dir().join("runtime"),
+        client_dir: &extframework_dir(),
+        mods_repo: &yakclient_dir().join("repo"),
+        client_version: &client_version,
+        mod_sets: &mod_sets,
     };
 
-    Ok(())
+    gc::collect_garbage(&roots, delete).map_err(IoError)
+}
+
+// Zips up everything support asks for about an instance, with `sessions` of its most recent logs,
+// and returns where it was written
+#[tauri::command]
+pub async fn export_diagnostic_bundle(
+    instance: Option<String>,
+    sessions: Option<usize>,
+    persisted_data: State<'_, PersistedData>,
+    downloads: State<'_, DownloadScheduler>,
+) -> Result<String, ClientError> {
+    let instance = find_instance(&persisted_data, instance.as_deref()).map_err(InstanceErr)?;
+    let client_version = get_client_version().await?;
+
+    let dir = yakclient_dir().join("diagnostics");
+    create_dir_all(&dir).map_err(IoError)?;
+    let path = dir.join(format!("{}-{}.zip", instance.id, file_timestamp()));
+
+    let sources = BundleSources {
+        instance: &instance,
+        minecraft_dir: &minecraft_dir(),
+        runtime_dir: &yakclient_dir().join("runtime"),
+        mods_repo: &yakclient_dir().join("repo"),
+        session_logs_dir: &session_logs_dir(),
+        config: persisted_data.snapshot(),
+        client_version: &client_version,
+        sessions: sessions.unwrap_or(3),
+    };
+    let redactor = Redactor::from_persisted(&persisted_data);
+
+    let problems = write_diagnostic_bundle(&path, sources, &redactor, &downloads)
+        .await
+        .map_err(IoError)?;
+    for problem in problems {
+        println!("Left out of the diagnostic bundle: {}", problem);
+    }
+
+    let _ = open::that(&dir);
+
+    Ok(path.to_string_lossy().to_string())
 }
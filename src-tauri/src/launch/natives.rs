@@ -0,0 +1,190 @@
+use crate::launch::minecraft::{Artifact, Error};
+use crate::util::hash::sha1_bytes;
+use crate::util::rand::generate_random_id;
+use std::fs::{create_dir_all, remove_dir_all, rename, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+// A native classifier jar along with the entries that shouldn't be extracted from it
+#[derive(Debug, Clone)]
+pub struct NativeArtifact {
+    pub artifact: Artifact,
+    pub exclude: Vec<String>,
+}
+
+// Natives are extracted per version into a directory named after everything that goes into it, so
+// versions can run side by side and an unchanged set is never extracted twice.
+pub fn natives_dir(path: &Path, version: &str, natives: &[NativeArtifact]) -> PathBuf {
+    let mut key = natives
+        .iter()
+        .map(|native| {
+            let id = native.artifact.sha1.as_ref().unwrap_or(&native.artifact.name);
+
+            format!("{}:{}", id, native.exclude.join(","))
+        })
+        .collect::<Vec<_>>();
+    key.sort();
+
+    let hash = sha1_bytes(key.join("\n").as_bytes());
+
+    path.join("natives").join(format!("{}-{}", version, &hash[0..12]))
+}
+
+pub fn extract_natives(
+    path: &Path,
+    version: &str,
+    natives: &[NativeArtifact],
+) -> Result<PathBuf, Error> {
+    let target = natives_dir(path, version, natives);
+    if target.exists() {
+        return Ok(target);
+    }
+
+    // Extracted aside and moved into place once complete, so a directory that exists is always whole
+    let staging = target.with_file_name(format!(
+        "{}.tmp-{}",
+        target.file_name().unwrap().to_str().unwrap(),
+        generate_random_id(6)
+    ));
+    create_dir_all(&staging)?;
+
+    let result = natives
+        .iter()
+        .try_for_each(|native| extract_native(native, &staging))
+        .and_then(|_| match rename(&staging, &target) {
+            // Another launch got there first, its copy is just as good
+            Err(_) if target.exists() => Ok(remove_dir_all(&staging)?),
+            result => Ok(result?),
+        });
+
+    if result.is_err() {
+        let _ = remove_dir_all(&staging);
+    }
+    result?;
+
+    Ok(target)
+}
+
+// Extracts the natives again from scratch, for when the directory was tampered with after the fact
+pub fn reextract_natives(
+    path: &Path,
+    version: &str,
+    natives: &[NativeArtifact],
+) -> Result<PathBuf, Error> {
+    let target = natives_dir(path, version, natives);
+    if target.exists() {
+        remove_dir_all(&target)?;
+    }
+
+    extract_natives(path, version, natives)
+}
+
+// Whether every file the jars would extract is there with the right size. A jar that can't be read
+// counts as not intact, it has to be downloaded and extracted again anyway.
+pub fn natives_intact(path: &Path, version: &str, natives: &[NativeArtifact]) -> bool {
+    let target = natives_dir(path, version, natives);
+    if !target.is_dir() {
+        return false;
+    }
+
+    natives.iter().all(|native| native_intact(native, &target).unwrap_or(false))
+}
+
+fn native_intact(native: &NativeArtifact, target: &Path) -> Result<bool, Error> {
+    let mut zip = ZipArchive::new(File::open(&native.artifact.path)?).map_err(Error::Zip)?;
+
+    for i in 0..zip.len() {
+        let entry = zip.by_index(i).map_err(Error::Zip)?;
+
+        let name = entry.name().to_string();
+        if entry.is_dir() || native.exclude.iter().any(|prefix| name.starts_with(prefix)) {
+            continue;
+        }
+
+        let Some(relative) = entry.enclosed_name() else {
+            continue;
+        };
+
+        match target.join(relative).metadata() {
+            Ok(metadata) if metadata.is_file() && metadata.len() == entry.size() => {}
+            _ => return Ok(false),
+        }
+    }
+
+    Ok(true)
+}
+
+fn extract_native(native: &NativeArtifact, target: &Path) -> Result<(), Error> {
+    let mut zip = ZipArchive::new(File::open(&native.artifact.path)?).map_err(Error::Zip)?;
+
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(Error::Zip)?;
+
+        let name = entry.name().to_string();
+        if entry.is_dir() || native.exclude.iter().any(|prefix| name.starts_with(prefix)) {
+            continue;
+        }
+
+        // Entries that would land outside the directory are skipped
+        let Some(relative) = entry.enclosed_name() else {
+            continue;
+        };
+        let destination = target.join(relative);
+
+        if let Some(parent) = destination.parent() {
+            create_dir_all(parent)?;
+        }
+        io::copy(&mut entry, &mut File::create(&destination)?)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    #[test]
+    fn test_extract_natives() {
+        let path = PathBuf::from("tests/natives");
+        let jar_path = path.join("lwjgl-platform-natives-linux.jar");
+        create_dir_all(&path).unwrap();
+
+        let mut jar = ZipWriter::new(File::create(&jar_path).unwrap());
+        jar.start_file("liblwjgl.so", SimpleFileOptions::default()).unwrap();
+        jar.write_all(b"native").unwrap();
+        jar.start_file("META-INF/MANIFEST.MF", SimpleFileOptions::default()).unwrap();
+        jar.write_all(b"Manifest-Version: 1.0").unwrap();
+        jar.finish().unwrap();
+
+        let natives = vec![NativeArtifact {
+            artifact: Artifact {
+                name: "org.lwjgl.lwjgl:lwjgl-platform:2.9.4:natives-linux".to_string(),
+                url: "https://libraries.minecraft.net/".to_string(),
+                path: jar_path,
+                size: None,
+                sha1: Some("b84d5102b9dbfabfeb5e43c7e2828d98a7fc80e0".to_string()),
+            },
+            exclude: vec!["META-INF/".to_string()],
+        }];
+
+        let _ = remove_dir_all(natives_dir(&path, "1.8.9", &natives));
+        let extracted = extract_natives(&path, "1.8.9", &natives).unwrap();
+
+        assert!(extracted.join("liblwjgl.so").exists());
+        assert!(!extracted.join("META-INF").exists());
+        assert!(natives_intact(&path, "1.8.9", &natives));
+
+        std::fs::write(extracted.join("liblwjgl.so"), b"").unwrap();
+        assert!(!natives_intact(&path, "1.8.9", &natives));
+
+        let extracted = reextract_natives(&path, "1.8.9", &natives).unwrap();
+        assert!(natives_intact(&path, "1.8.9", &natives));
+        assert_eq!(std::fs::read(extracted.join("liblwjgl.so")).unwrap(), b"native");
+        assert_eq!(extracted, extract_natives(&path, "1.8.9", &natives).unwrap());
+    }
+}
@@ -0,0 +1,154 @@
+use crate::launch::minecraft::Features;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Per-launch choices that switch on the feature-gated arguments in a version JSON
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchOptions {
+    pub resolution: Option<Resolution>,
+    #[serde(default)]
+    pub demo: bool,
+    pub quick_play: Option<QuickPlay>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+// Where the game should go straight into once it has started
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", content = "target", rename_all = "camelCase")]
+pub enum QuickPlay {
+    // The name of the world's folder in `saves`
+    Singleplayer(String),
+    // A server address, optionally with a port
+    Multiplayer(String),
+    // A realm id
+    Realms(String),
+}
+
+impl LaunchOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(resolution) = &self.resolution {
+            if resolution.width == 0 || resolution.height == 0 {
+                return Err("The window resolution must be larger than 0x0".to_string());
+            }
+        }
+
+        if let Some(quick_play) = &self.quick_play {
+            let target = match quick_play {
+                QuickPlay::Singleplayer(it) => it,
+                QuickPlay::Multiplayer(it) => it,
+                QuickPlay::Realms(it) => it,
+            };
+
+            if target.trim().is_empty() {
+                return Err("Quick Play needs a world, server or realm to join".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn features(&self) -> Features {
+        let quick_play = |matches: fn(&QuickPlay) -> bool| {
+            Some(self.quick_play.as_ref().is_some_and(matches))
+        };
+
+        Features {
+            is_demo_user: Some(self.demo),
+            has_custom_resolution: Some(self.resolution.is_some()),
+            has_quick_plays_support: Some(self.quick_play.is_some()),
+            is_quick_play_singleplayer: quick_play(|it| matches!(it, QuickPlay::Singleplayer(_))),
+            is_quick_play_multiplayer: quick_play(|it| matches!(it, QuickPlay::Multiplayer(_))),
+            is_quick_play_realms: quick_play(|it| matches!(it, QuickPlay::Realms(_))),
+        }
+    }
+
+    // Values for the placeholders the enabled features bring in
+    pub fn variables(&self, game_dir: &Path) -> Vec<(&'static str, String)> {
+        let mut variables = Vec::new();
+
+        if let Some(resolution) = &self.resolution {
+            variables.push(("resolution_width", resolution.width.to_string()));
+            variables.push(("resolution_height", resolution.height.to_string()));
+        }
+
+        if let Some(quick_play) = &self.quick_play {
+            // The game logs what it joined here. Game directories don't have to be valid UTF-8
+            // on Linux, where only this log would end up somewhere else.
+            let log_path = game_dir.join("quickPlay").join("log.json");
+            variables.push(("quickPlayPath", log_path.to_string_lossy().to_string()));
+
+            match quick_play {
+                QuickPlay::Singleplayer(world) => {
+                    variables.push(("quickPlaySingleplayer", world.clone()))
+                }
+                QuickPlay::Multiplayer(server) => {
+                    variables.push(("quickPlayMultiplayer", server.clone()))
+                }
+                QuickPlay::Realms(realm) => variables.push(("quickPlayRealms", realm.clone())),
+            }
+        }
+
+        variables
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::launch::minecraft::{Argument, FormatForCommand};
+    use crate::launch::rules::RuleContext;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_feature_arguments() {
+        // The tail of the game arguments in 1.21.1
+        let args: Vec<Argument> = serde_json::from_str(r#"[
+            "--version", "${version_name}",
+            {"rules": [{"action": "allow", "features": {"is_demo_user": true}}], "value": "--demo"},
+            {"rules": [{"action": "allow", "features": {"has_custom_resolution": true}}], "value": ["--width", "${resolution_width}", "--height", "${resolution_height}"]},
+            {"rules": [{"action": "allow", "features": {"has_quick_plays_support": true}}], "value": ["--quickPlayPath", "${quickPlayPath}"]},
+            {"rules": [{"action": "allow", "features": {"is_quick_play_singleplayer": true}}], "value": ["--quickPlaySingleplayer", "${quickPlaySingleplayer}"]},
+            {"rules": [{"action": "allow", "features": {"is_quick_play_multiplayer": true}}], "value": ["--quickPlayMultiplayer", "${quickPlayMultiplayer}"]},
+            {"rules": [{"action": "allow", "features": {"is_quick_play_realms": true}}], "value": ["--quickPlayRealms", "${quickPlayRealms}"]}
+        ]"#).unwrap();
+
+        let options = LaunchOptions {
+            resolution: Some(Resolution { width: 1280, height: 720 }),
+            demo: false,
+            quick_play: Some(QuickPlay::Multiplayer("localhost:25565".to_string())),
+        };
+        assert!(options.validate().is_ok());
+
+        let mut values = HashMap::from([("version_name", "1.21.1".to_string())]);
+        values.extend(options.variables(Path::new("mc")));
+
+        let context = RuleContext {
+            features: options.features(),
+            ..RuleContext::default()
+        };
+        let result = args.chunks(2).collect::<Vec<&[Argument]>>().format(&values, &context);
+
+        assert_eq!(result, vec![
+            "--version", "1.21.1",
+            "--width", "1280", "--height", "720",
+            "--quickPlayPath", Path::new("mc").join("quickPlay").join("log.json").to_str().unwrap(),
+            "--quickPlayMultiplayer", "localhost:25565",
+        ]);
+
+        #[cfg(unix)]
+        {
+            use std::ffi::OsStr;
+            use std::os::unix::ffi::OsStrExt;
+
+            let game_dir = Path::new(OsStr::from_bytes(b"/home/\xffmc"));
+            let variables = options.variables(game_dir);
+            assert!(variables.iter().any(|(name, _)| *name == "quickPlayPath"));
+        }
+    }
+}
@@ -0,0 +1,579 @@
+use crate::launch::redact::Redactor;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process::{ChildStderr, ChildStdout};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+// Lines waiting to be parsed. Once full the readers stop reading, and the game blocks on its output
+// until the launcher catches up.
+const LINE_BUFFER_SIZE: usize = 1024;
+// Records are sent to the frontend at most this often, or sooner once a batch is full
+const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+const MAX_BATCH_SIZE: usize = 256;
+
+// Longer lines are split, so output that never ends a line can't grow the buffer forever
+const MAX_LINE_LENGTH: usize = 64 * 1024;
+
+// An XML event that grows past this is taken to never be closed, and its lines are passed on as
+// plain output
+const MAX_XML_EVENT_SIZE: usize = 16 * MAX_LINE_LENGTH;
+
+// How much of a process's output is kept to be analyzed once it exits
+const OUTPUT_TAIL_SIZE: usize = 256 * 1024;
+
+// One log event, with the stack trace that followed it. Lines that aren't from Log4j only have a
+// message.
+#[derive(Serialize, Clone, Debug, PartialEq, Default)]
+pub struct LogRecord {
+    pub is_err: bool,
+    pub timestamp: Option<String>,
+    pub thread: Option<String>,
+    pub level: Option<String>,
+    pub logger: Option<String>,
+    pub message: String,
+    pub stack_trace: Vec<String>,
+}
+
+// What the console channel receives
+#[derive(Serialize, Clone, Debug)]
+pub struct LogBatch {
+    pub records: Vec<LogRecord>,
+}
+
+struct OutputLine {
+    is_err: bool,
+    text: String,
+}
+
+// The end of what a process printed to stdout and stderr
+#[derive(Clone, Default)]
+pub struct OutputTail(Arc<std::sync::Mutex<VecDeque<u8>>>);
+
+impl OutputTail {
+    fn push_line(&self, line: &str) {
+        let mut tail = self.0.lock().unwrap();
+        tail.extend(line.as_bytes());
+        tail.push_back(b'\n');
+
+        let overflow = tail.len().saturating_sub(OUTPUT_TAIL_SIZE);
+        tail.drain(..overflow);
+    }
+
+    pub fn text(&self) -> String {
+        let tail = self.0.lock().unwrap();
+        String::from_utf8_lossy(&tail.iter().copied().collect::<Vec<_>>()).into_owned()
+    }
+}
+
+// `[12:01:44] [Render thread/INFO]: Message`, newer versions add `(logger)` before the colon
+fn pattern_layout() -> Regex {
+    Regex::new(r"^\[(?P<timestamp>[^\]]+)\] \[(?P<thread>.+?)/(?P<level>TRACE|DEBUG|INFO|WARN|ERROR|FATAL)\](?: \((?P<logger>[^)]+)\))?: (?P<message>.*)$").unwrap()
+}
+
+// Lines that carry on the stack trace of the record before them
+fn stack_trace_line() -> Regex {
+    Regex::new(r"^(?:\s+at |\s+\.\.\. \d+ (?:more|common frames omitted)|Caused by: |\s+Suppressed: |\s+Caused by: )").unwrap()
+}
+
+// The first line of a stack trace, `java.lang.IllegalStateException: Message`
+fn exception_line() -> Regex {
+    Regex::new(r"^(?:Exception in thread .+ )?[\w$]+(?:\.[\w$]+)+(?:Exception|Error|Throwable)(?::.*)?$").unwrap()
+}
+
+// Turns the lines of one stream into records. A record is held back until the next one starts, since
+// its stack trace comes in the lines after it.
+pub struct LogParser {
+    is_err: bool,
+    pending: Option<LogRecord>,
+    // The lines of an XML event that hasn't been closed yet
+    xml_event: Option<String>,
+    pattern_layout: Regex,
+    stack_trace_line: Regex,
+    exception_line: Regex,
+}
+
+impl LogParser {
+    pub fn new(is_err: bool) -> Self {
+        LogParser {
+            is_err,
+            pending: None,
+            xml_event: None,
+            pattern_layout: pattern_layout(),
+            stack_trace_line: stack_trace_line(),
+            exception_line: exception_line(),
+        }
+    }
+
+    // Returns the records this line finished, usually none or one
+    pub fn push(&mut self, line: &str) -> Vec<LogRecord> {
+        if self.xml_event.is_none() && is_xml_event_start(line) {
+            self.xml_event = Some(String::new());
+        }
+
+        if let Some(event) = &mut self.xml_event {
+            if !event.is_empty() {
+                event.push('\n');
+            }
+            event.push_str(line);
+
+            if is_xml_event_end(line) {
+                let event = self.xml_event.take().unwrap();
+                return self.start(parse_xml_event(&event, self.is_err)).into_iter().collect();
+            }
+
+            if event.len() > MAX_XML_EVENT_SIZE {
+                let event = self.xml_event.take().unwrap();
+                return event.lines().filter_map(|line| self.start(self.plain(line))).collect();
+            }
+
+            return vec![];
+        }
+
+        if let Some(captures) = self.pattern_layout.captures(line) {
+            let group = |name: &str| captures.name(name).map(|it| it.as_str().to_string());
+
+            return self.start(LogRecord {
+                is_err: self.is_err,
+                timestamp: group("timestamp"),
+                thread: group("thread"),
+                level: group("level"),
+                logger: group("logger"),
+                message: group("message").unwrap_or_default(),
+                stack_trace: vec![],
+            }).into_iter().collect();
+        }
+
+        if let Some(pending) = &mut self.pending {
+            let continues = self.stack_trace_line.is_match(line)
+                // An exception logged along with a message starts right after it
+                || (pending.level.is_some()
+                    && pending.stack_trace.is_empty()
+                    && self.exception_line.is_match(line));
+
+            if continues {
+                pending.stack_trace.push(line.to_string());
+                return vec![];
+            }
+        }
+
+        self.start(self.plain(line)).into_iter().collect()
+    }
+
+    fn start(&mut self, record: LogRecord) -> Option<LogRecord> {
+        self.pending.replace(record)
+    }
+
+    fn plain(&self, line: &str) -> LogRecord {
+        LogRecord {
+            is_err: self.is_err,
+            message: line.to_string(),
+            ..LogRecord::default()
+        }
+    }
+
+    // The held back record, once the stream has gone quiet. An XML event still being read is left
+    // alone.
+    pub fn flush(&mut self) -> Option<LogRecord> {
+        self.pending.take()
+    }
+
+    // Everything left once the stream has ended
+    pub fn finish(&mut self) -> Vec<LogRecord> {
+        let mut records = Vec::new();
+        records.extend(self.pending.take());
+
+        if let Some(event) = self.xml_event.take() {
+            records.push(self.plain(&event));
+        }
+
+        records
+    }
+}
+
+// Our config uses Log4j's `XMLLayout`, older versions' own configs use `LegacyXMLLayout`
+fn is_xml_event_start(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("<log4j:Event ") || line.starts_with("<Event ")
+}
+
+fn is_xml_event_end(line: &str) -> bool {
+    line.contains("</log4j:Event>") || line.contains("</Event>")
+}
+
+fn parse_xml_event(event: &str, is_err: bool) -> LogRecord {
+    // Attributes are only read from the opening tag, messages can contain anything
+    let header = event.lines().next().unwrap_or_default();
+    let attribute = |names: &[&str]| {
+        names.iter().find_map(|name| {
+            let start = header.find(&format!(" {}=\"", name))? + name.len() + 3;
+            let end = start + header[start..].find('"')?;
+
+            Some(unescape_xml(&header[start..end]))
+        })
+    };
+
+    let message = ["log4j:Message", "Message"]
+        .iter()
+        .find_map(|tag| xml_element(event, tag))
+        .unwrap_or_default();
+
+    // Legacy events have the whole trace as text, newer ones list every frame as an element
+    let stack_trace = if let Some(throwable) = xml_element(event, "log4j:Throwable") {
+        throwable.lines().map(|it| it.to_string()).filter(|it| !it.trim().is_empty()).collect()
+    } else {
+        xml_thrown(event)
+    };
+
+    LogRecord {
+        is_err,
+        timestamp: attribute(&["timestamp", "timeMillis"]),
+        thread: attribute(&["thread"]),
+        level: attribute(&["level"]),
+        logger: attribute(&["logger", "loggerName"]),
+        message,
+        stack_trace,
+    }
+}
+
+fn xml_element(event: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let start = event.find(&open)? + open.len();
+    let end = start + event[start..].find(&format!("</{}>", tag))?;
+
+    let text = &event[start..end];
+    Some(match text.strip_prefix("<![CDATA[").and_then(|it| it.strip_suffix("]]>")) {
+        Some(data) => data.to_string(),
+        None => unescape_xml(text),
+    })
+}
+
+fn xml_thrown(event: &str) -> Vec<String> {
+    let mut stack_trace = Vec::new();
+
+    for line in event.lines().map(|it| it.trim()) {
+        let attribute = |name: &str| {
+            let start = line.find(&format!(" {}=\"", name))? + name.len() + 3;
+            let end = start + line[start..].find('"')?;
+
+            Some(unescape_xml(&line[start..end]))
+        };
+
+        if line.starts_with("<Thrown ") || line.starts_with("<Cause ") {
+            let name = attribute("name").unwrap_or_default();
+            let header = match attribute("message") {
+                Some(message) => format!("{}: {}", name, message),
+                None => name,
+            };
+
+            if line.starts_with("<Cause ") {
+                stack_trace.push(format!("Caused by: {}", header));
+            } else {
+                stack_trace.push(header);
+            }
+        } else if line.starts_with("<ExtendedStackTraceItem ") {
+            let location = match (attribute("file"), attribute("line")) {
+                (Some(file), Some(line)) => format!("{}:{}", file, line),
+                (Some(file), None) => file,
+                _ => "Unknown Source".to_string(),
+            };
+
+            stack_trace.push(format!(
+                "\tat {}.{}({})",
+                attribute("class").unwrap_or_default(),
+                attribute("method").unwrap_or_default(),
+                location
+            ));
+        }
+    }
+
+    stack_trace
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#10;", "\n")
+        .replace("&#13;", "\r")
+        .replace("&amp;", "&")
+}
+
+async fn read_lines(stream: impl AsyncRead + Unpin, is_err: bool, lines: mpsc::Sender<OutputLine>) {
+    let mut reader = BufReader::new(stream);
+    let mut buffer = Vec::new();
+
+    loop {
+        let available = match reader.fill_buf().await {
+            Ok([]) | Err(_) => break,
+            Ok(available) => available,
+        };
+
+        let (consumed, complete) = match available.iter().position(|it| *it == b'\n') {
+            Some(end) => (end + 1, true),
+            None => (available.len(), false),
+        };
+        buffer.extend_from_slice(&available[..consumed]);
+        reader.consume(consumed);
+
+        while buffer.len() > MAX_LINE_LENGTH {
+            let end = line_split_point(&buffer);
+            let rest = buffer.split_off(end);
+            if !send_line(&lines, is_err, &buffer).await {
+                return;
+            }
+            buffer = rest;
+        }
+
+        if complete {
+            if !send_line(&lines, is_err, &buffer).await {
+                return;
+            }
+            buffer.clear();
+        }
+    }
+
+    // Whatever was printed last without a newline
+    if !buffer.is_empty() {
+        send_line(&lines, is_err, &buffer).await;
+    }
+}
+
+// Where a line that is too long is cut, moved back so characters aren't split in two
+fn line_split_point(buffer: &[u8]) -> usize {
+    let mut end = MAX_LINE_LENGTH;
+    // UTF-8 continuation bytes look like 0b10xxxxxx, there are at most 3 after the first byte
+    while end > MAX_LINE_LENGTH - 3 && buffer[end] & 0b1100_0000 == 0b1000_0000 {
+        end -= 1;
+    }
+
+    end
+}
+
+async fn send_line(lines: &mpsc::Sender<OutputLine>, is_err: bool, line: &[u8]) -> bool {
+    // Only whole lines are decoded, so characters can't be split between reads
+    let text = String::from_utf8_lossy(line)
+        .trim_end_matches(['\n', '\r'])
+        .to_string();
+
+    lines.send(OutputLine { is_err, text }).await.is_ok()
+}
+
+// Parses lines as they come in and sends them on in batches, writing them to the session log as they
+// were printed. Only what's sent on is redacted. Keeps reading after the frontend has gone away,
+// otherwise the game would block on a full pipe.
+async fn forward_records(
+    mut lines: mpsc::Receiver<OutputLine>,
+    channel: Channel<LogBatch>,
+    tail: OutputTail,
+    log: Option<File>,
+    redactor: Option<Arc<Redactor>>,
+) {
+    let mut parsers = [LogParser::new(false), LogParser::new(true)];
+    let mut batch = Vec::new();
+    let mut last_sent = Instant::now();
+    let mut channel = Some(channel);
+    let mut log = log.map(BufWriter::new);
+
+    loop {
+        let finished = match timeout(FLUSH_INTERVAL, lines.recv()).await {
+            Ok(Some(line)) => {
+                tail.push_line(&line.text);
+                if let Some(writer) = &mut log {
+                    if let Err(e) = writeln!(writer, "{}", line.text) {
+                        println!("Failed to write the session log: {}", e);
+                        log = None;
+                    }
+                }
+                batch.extend(parsers[line.is_err as usize].push(&line.text));
+                false
+            }
+            Ok(None) => true,
+            // Nothing more is coming for now, so whatever is held back is complete
+            Err(_) => {
+                batch.extend(parsers.iter_mut().filter_map(|it| it.flush()));
+                false
+            }
+        };
+
+        if finished {
+            batch.extend(parsers.iter_mut().flat_map(|it| it.finish()));
+        }
+
+        let due = batch.len() >= MAX_BATCH_SIZE || last_sent.elapsed() >= FLUSH_INTERVAL || finished;
+        if due && !batch.is_empty() {
+            let mut records = std::mem::take(&mut batch);
+            if let Some(redactor) = &redactor {
+                records.iter_mut().for_each(|it| redactor.redact_record(it));
+            }
+            if let Some(sender) = &channel {
+                if sender.send(LogBatch { records }).is_err() {
+                    channel = None;
+                }
+            }
+            last_sent = Instant::now();
+
+            if let Some(writer) = &mut log {
+                let _ = writer.flush();
+            }
+        }
+
+        if finished {
+            break;
+        }
+    }
+}
+
+// Reads both streams until the process closes them, sending what it logs to `channel`
+pub fn capture_output(
+    stdout: ChildStdout,
+    stderr: ChildStderr,
+    channel: Channel<LogBatch>,
+    log: Option<File>,
+    redactor: Option<Arc<Redactor>>,
+) -> OutputTail {
+    let tail = OutputTail::default();
+    let (sender, receiver) = mpsc::channel(LINE_BUFFER_SIZE);
+
+    let stdout_lines = sender.clone();
+    tauri::async_runtime::spawn(async move {
+        match tokio::process::ChildStdout::from_std(stdout) {
+            Ok(stdout) => read_lines(stdout, false, stdout_lines).await,
+            Err(e) => println!("Failed to read the game's stdout: {}", e),
+        }
+    });
+    tauri::async_runtime::spawn(async move {
+        match tokio::process::ChildStderr::from_std(stderr) {
+            Ok(stderr) => read_lines(stderr, true, sender).await,
+            Err(e) => println!("Failed to read the game's stderr: {}", e),
+        }
+    });
+
+    tauri::async_runtime::spawn(forward_records(receiver, channel, tail.clone(), log, redactor));
+
+    tail
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(lines: &[&str]) -> Vec<LogRecord> {
+        let mut parser = LogParser::new(false);
+        let mut records = lines.iter().flat_map(|it| parser.push(it)).collect::<Vec<_>>();
+        records.extend(parser.finish());
+
+        records
+    }
+
+    #[test]
+    fn test_parse_log_records() {
+        let records = parse(&[
+            "[12:01:44] [Render thread/INFO] (Minecraft): Setting user: Player",
+            "[12:01:45] [main/ERROR]: Failed to load a resource pack",
+            "java.io.FileNotFoundException: pack.mcmeta",
+            "\tat java.base/java.io.FileInputStream.open0(Native Method)",
+            "Caused by: java.lang.IllegalStateException",
+            "\t... 4 more",
+            "Plain output",
+            "java.lang.RuntimeException: Not part of a record",
+        ]);
+
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].thread.as_deref(), Some("Render thread"));
+        assert_eq!(records[0].logger.as_deref(), Some("Minecraft"));
+        assert_eq!(records[0].message, "Setting user: Player");
+
+        assert_eq!(records[1].level.as_deref(), Some("ERROR"));
+        assert!(records[1].logger.is_none());
+        assert_eq!(records[1].stack_trace.len(), 4);
+        assert_eq!(records[1].stack_trace[0], "java.io.FileNotFoundException: pack.mcmeta");
+
+        assert_eq!(records[2].message, "Plain output");
+        assert!(records[2].level.is_none());
+        assert!(records[3].stack_trace.is_empty());
+
+        let records = parse(&[
+            "<log4j:Event logger=\"ees\" timestamp=\"1731000000000\" level=\"WARN\" thread=\"Server thread\">",
+            "  <log4j:Message><![CDATA[Can't keep up! <is the server overloaded?>]]></log4j:Message>",
+            "  <log4j:Throwable><![CDATA[java.lang.Exception: Slow",
+            "\tat ees.tick(SourceFile:12)",
+            "]]></log4j:Throwable>",
+            "</log4j:Event>",
+            "<Event xmlns=\"http://logging.apache.org/log4j/2.0/events\" timeMillis=\"1731000000001\" thread=\"main\" level=\"ERROR\" loggerName=\"net.minecraft.Main\">",
+            "  <Message>Crashed &amp; burned</Message>",
+            "  <Thrown name=\"java.lang.IllegalStateException\" message=\"Bad &quot;state&quot;\">",
+            "    <ExtendedStackTrace>",
+            "      <ExtendedStackTraceItem class=\"net.minecraft.Main\" method=\"main\" file=\"Main.java\" line=\"42\"/>",
+            "    </ExtendedStackTrace>",
+            "  </Thrown>",
+            "</Event>",
+        ]);
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].logger.as_deref(), Some("ees"));
+        assert_eq!(records[0].level.as_deref(), Some("WARN"));
+        assert_eq!(records[0].message, "Can't keep up! <is the server overloaded?>");
+        assert_eq!(records[0].stack_trace, vec!["java.lang.Exception: Slow", "\tat ees.tick(SourceFile:12)"]);
+
+        assert_eq!(records[1].timestamp.as_deref(), Some("1731000000001"));
+        assert_eq!(records[1].logger.as_deref(), Some("net.minecraft.Main"));
+        assert_eq!(records[1].message, "Crashed & burned");
+        assert_eq!(records[1].stack_trace, vec![
+            "java.lang.IllegalStateException: Bad \"state\"",
+            "\tat net.minecraft.Main.main(Main.java:42)",
+        ]);
+    }
+
+    #[test]
+    fn test_unclosed_xml_event() {
+        let mut parser = LogParser::new(false);
+        assert!(parser.push("<log4j:Event logger=\"ees\" level=\"INFO\" thread=\"main\">").is_empty());
+
+        let line = "x".repeat(MAX_LINE_LENGTH);
+        let mut records = vec![];
+        while records.is_empty() {
+            records = parser.push(&line);
+        }
+
+        // Everything but the last line, which is held back like any other record
+        assert_eq!(records.len(), MAX_XML_EVENT_SIZE / MAX_LINE_LENGTH);
+        assert!(records[0].message.starts_with("<log4j:Event "));
+        assert!(records.iter().skip(1).all(|it| it.message == line && it.level.is_none()));
+        assert!(parser.xml_event.is_none());
+
+        let records = parser.push("[12:01:44] [main/INFO]: Back to normal");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].message, line);
+        assert_eq!(parser.finish()[0].message, "Back to normal");
+    }
+
+    #[tokio::test]
+    async fn test_read_long_lines() {
+        // A character that would straddle the limit goes into the next part whole
+        let mut output = "a".repeat(MAX_LINE_LENGTH - 1) + "é" + &"b".repeat(MAX_LINE_LENGTH) + "\r\n";
+        output.push_str("short\nno newline");
+
+        let (sender, mut receiver) = mpsc::channel(16);
+        read_lines(output.as_bytes(), false, sender).await;
+
+        let mut lines = Vec::new();
+        while let Some(line) = receiver.recv().await {
+            lines.push(line.text);
+        }
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "a".repeat(MAX_LINE_LENGTH - 1));
+        assert!(lines[1].starts_with("éb"));
+        assert_eq!(lines[0].len() + lines[1].len() + lines[2].len(), 2 * MAX_LINE_LENGTH + 1);
+        assert_eq!(&lines[3..], ["short", "no newline"]);
+    }
+}
@@ -1,29 +1,74 @@
-use crate::launch::java::get_java_command;
+use crate::instances::Instance;
+use crate::launch::analyzer::Diagnosis;
+use crate::launch::crash::CrashSummary;
+use crate::launch::hooks::{split_command, LaunchHooks};
+use crate::launch::java::{get_java_executable, zulu_os_arch, zulu_os_name};
+use crate::launch::jvm::{total_memory, JvmOptions};
 use crate::launch::minecraft::{Argument, Arguments, FormatForCommand, MinecraftEnvironment, ValueType};
+use crate::launch::log_config::write_launcher_config;
+use crate::launch::options::LaunchOptions;
+use crate::launch::output::{capture_output, LogBatch, OutputTail};
+use crate::launch::redact::Redactor;
+use crate::launch::rules::RuleContext;
 use crate::launch::ClientError;
 use crate::launch::ClientError::{IoError, JreInstallError};
 use crate::minecraft_dir;
 use crate::state::{Extension, MinecraftAuthentication};
+use crate::task::download::DownloadScheduler;
+use crate::task::TaskManager;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::env::args;
-use std::io::{Read, Write};
-use std::path::PathBuf;
-use std::process::{Child, Command, Stdio};
-use std::str::from_utf8;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
 use std::sync::Arc;
-use std::{io, thread};
-use std::thread::sleep;
+use std::fs::File;
+use std::io;
 use std::time::Duration;
 use tauri::ipc::Channel;
 use tauri::Manager;
 use tokio::sync::Mutex;
-use crate::settings::DebuggerSettings;
+use crate::settings::{DebuggerSettings, JvmSettings, LoggingSettings};
+
+// Emitted as `process-exit` once a game process has been reaped
+#[derive(Clone, Serialize, Debug)]
+pub struct ProcessExitEvent {
+    pub id: String,
+    pub instance_id: String,
+    pub code: Option<i32>,
+    // The signal that ended the process, only ever set on Unix
+    pub signal: Option<i32>,
+    pub duration_ms: u64,
+    // The launcher was asked to stop it
+    pub stopped: bool,
+    // Only looked for when the process didn't exit cleanly or get stopped
+    pub crashes: Vec<CrashSummary>,
+    // Known problems found in the output and crash files
+    pub diagnoses: Vec<Diagnosis>,
+}
 
-#[derive(Clone, Serialize)]
-pub struct ProcessStdoutEvent {
-    pub is_err: bool,
-    pub frag: Vec<u8>,
+impl ProcessExitEvent {
+    // Without a status, when waiting on the process failed, neither the code nor the signal is known
+    pub fn new(id: String, instance_id: String, status: Option<ExitStatus>, duration_ms: u64) -> Self {
+        #[cfg(unix)]
+        let signal = {
+            use std::os::unix::process::ExitStatusExt;
+            status.and_then(|it| it.signal())
+        };
+        #[cfg(not(unix))]
+        let signal = None;
+
+        ProcessExitEvent {
+            id,
+            instance_id,
+            code: status.and_then(|it| it.code()),
+            signal,
+            duration_ms,
+            stopped: false,
+            crashes: vec![],
+            diagnoses: vec![],
+        }
+    }
 }
 
 // fn add_env_args<'a, 'b>(
@@ -48,56 +93,219 @@ pub struct ProcessStdoutEvent {
 //         .arg("-jar")
 // }
 
-struct ProcessStdEmitter {
-    handle_ref: Channel<ProcessStdoutEvent>,
-    is_err: bool,
+// Entries in `--classpath` are split the same way the JVM splits its own classpath
+#[cfg(target_os = "windows")]
+const CLASSPATH_SEPARATOR: &str = ";";
+#[cfg(not(target_os = "windows"))]
+const CLASSPATH_SEPARATOR: &str = ":";
+
+// Scripts read the access token from here rather than having it written into them
+pub const ACCESS_TOKEN_VARIABLE: &str = "MC_ACCESS_TOKEN";
+const REDACTED: &str = "<redacted>";
+
+// Everything `launch_process` would start the game with, fully resolved
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchCommand {
+    pub java: PathBuf,
+    pub jvm_args: Vec<String>,
+    pub client_args: Vec<String>,
+    // `-e`/`-r` pairs, one for each extension
+    pub extension_args: Vec<String>,
+    pub game_args: Vec<String>,
+    pub working_dir: PathBuf,
+    pub env: HashMap<String, String>,
+    // Runs the java command when it isn't empty
+    pub wrapper: Vec<String>,
+    pub hooks: LaunchHooks,
 }
 
-impl<'a> Write for ProcessStdEmitter {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        print!("{}", from_utf8(buf).unwrap());
-        self.handle_ref
-            .send(ProcessStdoutEvent {
-                is_err: self.is_err,
-                frag: Vec::from(buf),
-            })
-            .unwrap();
+impl LaunchCommand {
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        args.extend(self.jvm_args.iter().cloned());
+        args.extend(self.client_args.iter().cloned());
+        args.extend(self.extension_args.iter().cloned());
+        // Separator from MC args
+        args.push(":".to_string());
+        args.extend(self.game_args.iter().cloned());
+
+        args
+    }
+
+    // The wrapper, if there is one, followed by the java executable
+    pub fn program(&self) -> Vec<String> {
+        let mut program = self.wrapper.clone();
+        // Checked to be UTF-8 when the command was built
+        program.push(self.java.to_string_lossy().to_string());
+
+        program
+    }
+
+    pub fn command(&self) -> Command {
+        let program = self.program();
+        let mut command = Command::new(&program[0]);
+        command
+            .args(&program[1..])
+            .args(self.args())
+            .current_dir(&self.working_dir)
+            .envs(&self.env);
+
+        command
+    }
+
+    // Replaces every occurrence of the secret, including ones embedded in a larger argument
+    pub fn redact(&mut self, secret: &str) {
+        if secret.is_empty() {
+            return;
+        }
+
+        self.jvm_args
+            .iter_mut()
+            .chain(self.client_args.iter_mut())
+            .chain(self.extension_args.iter_mut())
+            .chain(self.game_args.iter_mut())
+            .chain(self.env.values_mut())
+            .for_each(|it| *it = it.replace(secret, REDACTED));
+    }
+
+    // A script that starts the game on its own, the secret is read from `ACCESS_TOKEN_VARIABLE`
+    pub fn script(&self, secret: Option<&str>) -> String {
+        let secret = secret.filter(|it| !it.is_empty());
+
+        if cfg!(target_os = "windows") {
+            self.batch_script(secret)
+        } else {
+            self.shell_script(secret)
+        }
+    }
+
+    fn shell_script(&self, secret: Option<&str>) -> String {
+        let quote = |value: &str| {
+            quote_with_secret(
+                value,
+                secret,
+                |part| format!("'{}'", part.replace('\'', "'\\''")),
+                &format!("\"${}\"", ACCESS_TOKEN_VARIABLE),
+            )
+        };
+
+        let mut script = String::from("#!/bin/sh\n");
+        if secret.is_some() {
+            script.push_str(&format!(
+                ": \"${{{0}:?Set {0} to your Minecraft access token}}\"\n",
+                ACCESS_TOKEN_VARIABLE
+            ));
+        }
+        script.push_str(&format!("cd {} || exit 1\n", quote(&self.working_dir.to_string_lossy())));
+        for (key, value) in self.sorted_env() {
+            script.push_str(&format!("export {}={}\n", key, quote(value)));
+        }
+
+        script.push_str(&format!(
+            "exec {}",
+            self.program().iter().map(|it| quote(it)).collect::<Vec<_>>().join(" ")
+        ));
+        for arg in self.args() {
+            script.push_str(&format!(" \\\n  {}", quote(&arg)));
+        }
+        script.push('\n');
 
-        return Ok(buf.len());
+        script
     }
 
-    fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+    fn batch_script(&self, secret: Option<&str>) -> String {
+        let quote = |value: &str| {
+            quote_with_secret(
+                value,
+                secret,
+                |part| format!("\"{}\"", part.replace('%', "%%").replace('"', "\"\"")),
+                &format!("\"%{}%\"", ACCESS_TOKEN_VARIABLE),
+            )
+        };
+
+        let mut script = String::from("@echo off\r\n");
+        if secret.is_some() {
+            script.push_str(&format!(
+                "if not defined {0} (echo Set {0} to your Minecraft access token & exit /b 1)\r\n",
+                ACCESS_TOKEN_VARIABLE
+            ));
+        }
+        script.push_str(&format!("cd /d {} || exit /b 1\r\n", quote(&self.working_dir.to_string_lossy())));
+        for (key, value) in self.sorted_env() {
+            script.push_str(&format!("set {}={}\r\n", key, value.replace('%', "%%")));
+        }
+
+        script.push_str(&self.program().iter().map(|it| quote(it)).collect::<Vec<_>>().join(" "));
+        for arg in self.args() {
+            script.push_str(&format!(" ^\r\n  {}", quote(&arg)));
+        }
+        script.push_str("\r\n");
+
+        script
+    }
+
+    fn sorted_env(&self) -> Vec<(&String, &String)> {
+        let mut env = self.env.iter().collect::<Vec<_>>();
+        env.sort();
+
+        env
+    }
+}
+
+// Quotes each piece around the secret, putting a reference to the variable holding it in between
+fn quote_with_secret(
+    value: &str,
+    secret: Option<&str>,
+    quote: impl Fn(&str) -> String,
+    reference: &str,
+) -> String {
+    match secret {
+        Some(secret) if value.contains(secret) => value
+            .split(secret)
+            .map(|part| quote(part))
+            .collect::<Vec<_>>()
+            .join(reference),
+        _ => quote(value),
     }
 }
 
-pub async fn launch_process(
-    version: String,
+pub fn join_classpath(classpath: &[PathBuf]) -> Result<String, ClientError> {
+    Ok(classpath
+        .iter()
+        .map(|s| utf8_path(s, "library").map(str::to_string))
+        .collect::<Result<Vec<String>, _>>()?
+        .join(CLASSPATH_SEPARATOR))
+}
+
+// Paths are passed to the game as arguments, which can't carry anything else
+fn utf8_path<'a>(path: &'a Path, name: &str) -> Result<&'a str, ClientError> {
+    path.to_str().ok_or_else(|| {
+        ClientError::InvalidLaunchOptions(format!("The {}'s path has to be valid UTF-8", name))
+    })
+}
+
+pub async fn build_launch_command(
+    instance: &Instance,
     java_dir: PathBuf,
     client_path: PathBuf,
     auth: &Option<MinecraftAuthentication>,
     extensions: &Vec<Extension>,
     env: &MinecraftEnvironment,
+    options: &LaunchOptions,
     debugger_settings: DebuggerSettings,
-) -> Result<Child, ClientError> {
+    logging_settings: LoggingSettings,
+    jvm_settings: JvmSettings,
+    tasks: &mut TaskManager,
+    downloads: &DownloadScheduler,
+) -> Result<LaunchCommand, ClientError> {
     // TODO cleaner version support
     let java_version = env.java_version.major_version.to_string();
+    let version = instance.version.clone();
+    let game_dir = &instance.game_dir;
+    let game_dir_str = utf8_path(game_dir, "game directory")?;
 
-    let os_name = if cfg!(target_os = "windows") {
-        "windows"
-    } else if cfg!(target_os = "macos") {
-        "mac"
-    } else {
-        "linux"
-    };
-
-    let os_arch = if cfg!(target_arch = "x86_64") {
-        "x64"
-    } else if cfg!(target_arch = "aarch64") {
-        "aarch64"
-    } else {
-        "x64"
-    };
+    let os_name = zulu_os_name();
+    let os_arch = zulu_os_arch();
 
     let mut classpath = env.libraries.clone();
     classpath.push(env.client_jar.clone());
@@ -106,34 +314,62 @@ pub async fn launch_process(
     let mut arg_variables = HashMap::from([
         ("version", version.clone()),
         ("version_name", version.clone()),
-        ("game_directory", minecraft_dir().to_str().unwrap().to_string()),
-        ("assets_root", env.asset_path.to_str().unwrap().to_string()),
+        ("game_directory", game_dir_str.to_string()),
+        ("assets_root", utf8_path(&env.asset_path, "assets directory")?.to_string()),
         ("assets_index_name", env.asset_index_name.clone()),
-        ("natives_directory", env.natives_path.to_str().unwrap().to_string()),
+        ("game_assets", utf8_path(&env.game_assets, "game assets directory")?.to_string()),
+        ("version_type", env.version_type.clone()),
+        ("user_properties", "{}".to_string()),
+        ("natives_directory", utf8_path(&env.natives_path, "natives directory")?.to_string()),
         ("launcher_name", "yakclient".to_string()),
         ("classpath", "~/nothing.jar".to_string()) // Just any temporary placeholder
     ]);
 
+    arg_variables.extend(options.variables(game_dir));
+
     if let Some(auth) = auth {
         arg_variables.insert("auth_player_name", auth.profile.name.clone());
         arg_variables.insert("auth_uuid", auth.profile.id.clone());
         arg_variables.insert("auth_access_token", auth.access_token.clone());
+        arg_variables.insert("user_type", "msa".to_string());
+        // What versions from before the access token was split out expect
+        arg_variables.insert(
+            "auth_session",
+            format!("token:{}:{}", auth.access_token, auth.profile.id),
+        );
     }
 
-    let mut command = get_java_command(java_version.as_str(), os_name, os_arch, java_dir)
+    let java = get_java_executable(java_version.as_str(), os_name, os_arch, java_dir, tasks, downloads)
         .await
         .map_err(|it| JreInstallError(it))?;
 
-    command.current_dir(minecraft_dir());
+    let rule_context = RuleContext::current(options.features());
+
+    let mut jvm_args = env.arguments.jvm.format(&arg_variables, &rule_context);
+
+    let total_memory = total_memory();
+    let jvm_options = JvmOptions::resolve(&jvm_settings, &instance.jvm, total_memory);
+    jvm_options
+        .validate(total_memory, Some(env.java_version.major_version))
+        .map_err(ClientError::InvalidLaunchOptions)?;
+    jvm_args.extend(jvm_options.jvm_arguments());
 
-    env.arguments.jvm
-        .apply(
-            &mut command,
-            &arg_variables,
+    if let Some(logging) = &env.logging {
+        let launcher_config = if logging_settings.launcher_config {
+            Some(write_launcher_config(&minecraft_dir()).map_err(IoError)?)
+        } else {
+            None
+        };
+
+        jvm_args.extend(
+            logging
+                .jvm_arguments(launcher_config.as_deref())
+                .map_err(ClientError::InvalidLaunchOptions)?,
         );
+    }
 
     if debugger_settings.enabled {
-        command.arg(
+        jvm_args.push(
             format!(
                 "-agentlib:jdwp=transport=dt_socket,server=y,suspend={},address={}",
                 if debugger_settings.suspend { "y" } else { "n" },
@@ -142,98 +378,211 @@ pub async fn launch_process(
         );
     }
 
-    command
-        .arg("-jar")
-        .arg(client_path.to_str().unwrap())
-        .arg("--main-class")
-        .arg(&env.main_class)
-        .arg("--mapping-namespace")
-        .arg("mojang:obfuscated")
-        .arg("--classpath")
-        .arg(classpath.iter().map(|s| s.to_str().unwrap().to_string()).collect::<Vec<String>>().join(";"))
-        .arg("--game-jar")
-        .arg(env.client_jar.to_str().unwrap())
-        .arg("--version")
-        .arg(version);
-
-    command.stdout(Stdio::piped()).stderr(Stdio::piped());
-
-    for x in extensions {
-        command.arg("-e");
-        command.arg(x.descriptor.as_str());
-        command.arg("-r");
-        command.arg(format!(
-            "{}@{}",
-            x.repository_type.cli_arg(),
-            x.repository.as_str()
-        ));
-    }
+    let client_args = vec![
+        "-jar".to_string(),
+        utf8_path(&client_path, "client")?.to_string(),
+        "--main-class".to_string(),
+        env.main_class.clone(),
+        "--mapping-namespace".to_string(),
+        "mojang:obfuscated".to_string(),
+        "--classpath".to_string(),
+        join_classpath(&classpath)?,
+        "--game-jar".to_string(),
+        utf8_path(&env.client_jar, "Minecraft jar")?.to_string(),
+        "--version".to_string(),
+        version,
+    ];
+
+    let extension_args = extensions
+        .iter()
+        .flat_map(|x| {
+            [
+                "-e".to_string(),
+                x.descriptor.clone(),
+                "-r".to_string(),
+                format!("{}@{}", x.repository_type.cli_arg(), x.repository.as_str()),
+            ]
+        })
+        .collect();
+
+    let wrapper = match &instance.launch.wrapper {
+        Some(wrapper) => split_command(wrapper).map_err(ClientError::InvalidLaunchOptions)?,
+        None => vec![],
+    };
 
-    // Separator from MC args
-    command.arg(":");
+    let mut hook_env = HashMap::from([
+        ("YAKCLIENT_INSTANCE_ID".to_string(), instance.id.clone()),
+        ("YAKCLIENT_INSTANCE_NAME".to_string(), instance.name.clone()),
+        ("YAKCLIENT_VERSION".to_string(), instance.version.clone()),
+        ("YAKCLIENT_GAME_DIR".to_string(), game_dir_str.to_string()),
+        ("YAKCLIENT_JAVA".to_string(), utf8_path(&java, "Java executable")?.to_string()),
+    ]);
+    hook_env.extend(instance.launch.env.clone());
 
-    env.arguments.game
+    let mut game_args = env.arguments.game
         .chunks(2)
         .collect::<Vec<&[Argument]>>()
-        .apply(
-            &mut command,
-            &arg_variables,
-        );
+        .format(&arg_variables, &rule_context);
+    game_args.extend(jvm_options.game_args);
+
+    Ok(LaunchCommand {
+        java,
+        jvm_args,
+        client_args,
+        extension_args,
+        game_args,
+        working_dir: game_dir.clone(),
+        env: instance.launch.env.clone(),
+        wrapper,
+        hooks: LaunchHooks {
+            pre_launch: instance.launch.pre_launch.clone(),
+            post_exit: instance.launch.post_exit.clone(),
+            env: hook_env,
+        },
+    })
+}
 
-    let child = command.spawn().map_err(|e| IoError(e))?;
+// Runs the pre-launch hook, and only starts the game if it succeeded
+pub async fn launch_process(command: &LaunchCommand) -> Result<Child, ClientError> {
+    if let Some(hook) = &command.hooks.pre_launch {
+        let output = command.hooks.run(hook, &command.working_dir, &[]).await.map_err(IoError)?;
+        if !output.success() {
+            return Err(ClientError::HookFailed(output.describe("pre-launch")));
+        }
+    }
+
+    let child = command
+        .command()
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| IoError(e))?;
 
     Ok(child)
 }
 
-pub fn capture_child(mut child: Child, channel: Channel<ProcessStdoutEvent>) -> Arc<Mutex<Child>> {
-    // Moved into the spawned thread.
-    let mut child_stdout = child.stdout.take().unwrap();
-    let mut child_stderr = child.stderr.take().unwrap();
+// Brings the process's window to the front. There is no portable way to do this, so it goes through
+// whatever each platform ships with (xdotool has to be installed on Linux).
+pub fn focus_window(pid: u32) -> io::Result<()> {
+    let status = if cfg!(target_os = "windows") {
+        Command::new("powershell")
+            .arg("-NoProfile")
+            .arg("-Command")
+            .arg(format!("if (-not (New-Object -ComObject WScript.Shell).AppActivate({})) {{ exit 1 }}", pid))
+            .status()?
+    } else if cfg!(target_os = "macos") {
+        Command::new("osascript")
+            .arg("-e")
+            .arg(format!(
+                "tell application \"System Events\" to set frontmost of (first process whose unix id is {}) to true",
+                pid
+            ))
+            .status()?
+    } else {
+        Command::new("xdotool")
+            .arg("search")
+            .arg("--pid")
+            .arg(pid.to_string())
+            .arg("windowactivate")
+            .status()?
+    };
 
-    let child = Arc::new(Mutex::new(child));
+    if !status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other, "Couldn't find a window to focus"));
+    }
 
-    let clone1 = channel.clone();
-    let mut emitter1 = ProcessStdEmitter {
-        handle_ref: clone1,
-        is_err: false,
-    };
+    Ok(())
+}
 
-    // TODO not super efficient
-    thread::spawn(move || {
-        loop {
-            sleep(Duration::from_millis(2));
-            let mut buffer: [u8; 64] = [0; 64];
-            // println!("Looping");
-            if let Ok(length) = child_stdout.read(&mut buffer) {
-                if length == 0 {
-                    break;
-                }
-
-                emitter1.write(&buffer[0..length]).unwrap();
-            }
+// Polls rather than blocking in `wait`, the lock has to stay free for the process to be killed
+pub async fn wait_for_exit(child: &Mutex<Child>) -> io::Result<ExitStatus> {
+    loop {
+        if let Some(status) = child.lock().await.try_wait()? {
+            return Ok(status);
         }
-    });
 
-    let clone2 = channel.clone();
-    let mut emitter2 = ProcessStdEmitter {
-        handle_ref: clone2,
-        is_err: true,
-    };
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
 
-    thread::spawn(move || {
-        loop {
-            sleep(Duration::from_millis(2));
-            let mut buffer: [u8; 64] = [0; 64];
-            if let Ok(length) = child_stderr.read(&mut buffer) {
-                if length == 0 {
-                    break;
-                }
-
-                emitter2.write(&buffer[0..length]).unwrap();
-            }
-        }
-    });
+pub fn capture_child(
+    mut child: Child,
+    channel: Channel<LogBatch>,
+    log: Option<File>,
+    redactor: Option<Arc<Redactor>>,
+) -> (Arc<Mutex<Child>>, OutputTail) {
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    let tail = capture_output(stdout, stderr, channel, log, redactor);
 
-    child
+    (Arc::new(Mutex::new(child)), tail)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_command() {
+        let classpath = vec![PathBuf::from("libraries/a.jar"), PathBuf::from("versions/1.21.1/1.21.1.jar")];
+        let joined = join_classpath(&classpath).unwrap();
+        assert_eq!(joined.split(CLASSPATH_SEPARATOR).count(), 2);
+
+        let mut command = LaunchCommand {
+            java: PathBuf::from("/jre/bin/java"),
+            jvm_args: vec!["-Xss1M".to_string()],
+            client_args: vec!["--classpath".to_string(), joined],
+            extension_args: vec![],
+            game_args: vec![
+                "--accessToken".to_string(),
+                "secret-token".to_string(),
+                "--session".to_string(),
+                "token:secret-token:uuid".to_string(),
+                "--username".to_string(),
+                "it's me".to_string(),
+            ],
+            working_dir: PathBuf::from("/minecraft"),
+            env: HashMap::new(),
+            wrapper: vec!["nice".to_string(), "-n".to_string(), "10".to_string()],
+            hooks: LaunchHooks::default(),
+        };
+
+        assert_eq!(command.args()[3], ":");
+        assert_eq!(command.command().get_program(), "nice");
+
+        let script = command.script(Some("secret-token"));
+        assert!(!script.contains("secret-token"));
+
+        if cfg!(not(target_os = "windows")) {
+            assert!(script.contains("'token:'\"$MC_ACCESS_TOKEN\"':uuid'"));
+            assert!(script.contains("'it'\\''s me'"));
+        }
+
+        command.redact("secret-token");
+        assert_eq!(command.game_args[1], REDACTED);
+        assert_eq!(command.game_args[3], "token:<redacted>:uuid");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exit_status() {
+        let child = Command::new("sh").arg("-c").arg("exit 3").spawn().unwrap();
+        let status = wait_for_exit(&Mutex::new(child)).await.unwrap();
+
+        let event = ProcessExitEvent::new("id".to_string(), "instance".to_string(), Some(status), 10);
+        assert_eq!(event.code, Some(3));
+        assert_eq!(event.signal, None);
+
+        let child = Arc::new(Mutex::new(Command::new("sleep").arg("5").spawn().unwrap()));
+        child.lock().await.kill().unwrap();
+        let status = wait_for_exit(&child).await.unwrap();
+
+        let event = ProcessExitEvent::new("id".to_string(), "instance".to_string(), Some(status), 10);
+        assert_eq!(event.code, None);
+        assert_eq!(event.signal, Some(9));
+
+        let event = ProcessExitEvent::new("id".to_string(), "instance".to_string(), None, 10);
+        assert_eq!((event.code, event.signal), (None, None));
+    }
+}
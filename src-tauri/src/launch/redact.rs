@@ -0,0 +1,248 @@
+use crate::launch::analyzer::Diagnosis;
+use crate::launch::crash::CrashSummary;
+use crate::launch::output::LogRecord;
+use crate::persist::PersistedData;
+use crate::settings::{RedactionSettings, UserSettings};
+use crate::state::MinecraftAuthentication;
+use regex::{escape, Captures, Regex};
+use std::path::PathBuf;
+
+const TOKEN: &str = "<token>";
+const UUID: &str = "<uuid>";
+const PLAYER: &str = "<player>";
+const USER: &str = "<user>";
+const HOME: &str = "~";
+const IP_ADDRESS: &str = "<ip>";
+const REDACTED: &str = "<redacted>";
+
+// What gets masked before logs leave the launcher, built from the settings and whoever is logged in
+pub struct Redactor {
+    rules: Vec<RedactionRule>,
+}
+
+struct RedactionRule {
+    regex: Regex,
+    // Can refer to the pattern's groups
+    replacement: String,
+    // Loopback and unspecified addresses don't say anything about anyone
+    keep_local: bool,
+}
+
+// Who the logs could give away, so they can be masked even where no pattern would catch them
+#[derive(Default)]
+pub struct KnownSecrets {
+    pub tokens: Vec<String>,
+    pub player_name: Option<String>,
+    pub player_uuid: Option<String>,
+    pub username: Option<String>,
+    pub home: Option<PathBuf>,
+}
+
+impl KnownSecrets {
+    pub fn current(auth: Option<&MinecraftAuthentication>) -> Self {
+        KnownSecrets {
+            tokens: auth
+                .map(|it| vec![it.access_token.clone(), it.refresh_token.clone()])
+                .unwrap_or_default(),
+            player_name: auth.map(|it| it.profile.name.clone()),
+            player_uuid: auth.map(|it| it.profile.id.clone()),
+            username: std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok(),
+            home: home::home_dir(),
+        }
+    }
+}
+
+impl Redactor {
+    pub fn new(settings: &RedactionSettings, secrets: &KnownSecrets) -> Self {
+        let mut rules = Vec::new();
+        if !settings.enabled {
+            return Redactor { rules };
+        }
+
+        // Custom patterns are checked when the settings are saved, ones saved before that was done are
+        // skipped rather than failing every export
+        let mut rule = |pattern: &str, replacement: &str, keep_local: bool| {
+            if let Ok(regex) = Regex::new(pattern) {
+                rules.push(RedactionRule {
+                    regex,
+                    replacement: replacement.to_string(),
+                    keep_local,
+                });
+            }
+        };
+
+        // Known values go first, the patterns after them only catch what's left
+        if settings.tokens {
+            for token in secrets.tokens.iter().filter(|it| !it.is_empty()) {
+                rule(&escape(token), TOKEN, false);
+            }
+            // Minecraft and Microsoft access tokens are JWTs
+            rule(r"eyJ[\w-]+\.[\w-]+\.[\w-]+", TOKEN, false);
+            rule(r"(--accessToken\s+)\S+", &format!("${{1}}{}", TOKEN), false);
+            rule(r#"(?i)((?:access|refresh)_?token["']?\s*[:=]\s*["']?)[\w.~+/-]{16,}"#, &format!("${{1}}{}", TOKEN), false);
+        }
+
+        if settings.home_paths {
+            if let Some(home) = secrets.home.as_ref().and_then(|it| it.to_str()).filter(|it| it.len() > 1) {
+                // Paths can show up with either separator on Windows, and escaped in JSON
+                let variants = [home.to_string(), home.replace('\\', "/"), home.replace('\\', "\\\\")];
+                for variant in variants {
+                    rule(&format!("(?i){}", escape(&variant)), HOME, false);
+                }
+            }
+        }
+
+        if settings.username {
+            if let Some(username) = secrets.username.as_ref().filter(|it| !it.is_empty()) {
+                rule(&format!(r"\b{}\b", escape(username)), USER, false);
+            }
+        }
+
+        if settings.player {
+            if let Some(name) = secrets.player_name.as_ref().filter(|it| !it.is_empty()) {
+                rule(&format!(r"\b{}\b", escape(name)), PLAYER, false);
+            }
+            // Profile ids are stored without dashes, which the UUID pattern below doesn't match
+            if let Some(uuid) = secrets.player_uuid.as_ref().filter(|it| !it.is_empty()) {
+                rule(&format!("(?i){}", escape(&uuid.replace('-', ""))), UUID, false);
+            }
+        }
+
+        if settings.uuids {
+            rule(r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b", UUID, false);
+        }
+
+        if settings.ip_addresses {
+            rule(r"\b(?:(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\.){3}(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\b", IP_ADDRESS, true);
+            rule(r"(?i)\b(?:[0-9a-f]{1,4}:){7}[0-9a-f]{1,4}\b", IP_ADDRESS, true);
+            rule(r"(?i)\b(?:[0-9a-f]{1,4}:){1,6}:(?:[0-9a-f]{1,4}:){0,5}[0-9a-f]{1,4}\b", IP_ADDRESS, true);
+        }
+
+        for pattern in &settings.custom {
+            rule(pattern, REDACTED, false);
+        }
+
+        Redactor { rules }
+    }
+
+    // Reads the settings and login, from wherever they were saved
+    pub fn from_persisted(persisted_data: &PersistedData) -> Self {
+        let settings: Option<UserSettings> = persisted_data.read_value("settings");
+        let auth: Option<MinecraftAuthentication> = persisted_data.read_value("ms_auth");
+
+        Redactor::new(
+            &settings.map(|it| it.logging.redaction).unwrap_or_default(),
+            &KnownSecrets::current(auth.as_ref()),
+        )
+    }
+
+    // Only when what the launcher shows is meant to be masked too
+    pub fn for_display(persisted_data: &PersistedData) -> Option<Self> {
+        let settings: Option<UserSettings> = persisted_data.read_value("settings");
+        let redaction = settings.map(|it| it.logging.redaction).unwrap_or_default();
+
+        (redaction.enabled && redaction.display).then(|| Redactor::from_persisted(persisted_data))
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+
+        for rule in &self.rules {
+            if !rule.regex.is_match(&text) {
+                continue;
+            }
+
+            text = rule
+                .regex
+                .replace_all(&text, |captures: &Captures| {
+                    if rule.keep_local && is_local_address(&captures[0]) {
+                        return captures[0].to_string();
+                    }
+
+                    let mut replacement = String::new();
+                    captures.expand(&rule.replacement, &mut replacement);
+                    replacement
+                })
+                .into_owned();
+        }
+
+        text
+    }
+
+    pub fn redact_record(&self, record: &mut LogRecord) {
+        record.message = self.redact(&record.message);
+        for line in &mut record.stack_trace {
+            *line = self.redact(line);
+        }
+    }
+
+    pub fn redact_crash(&self, crash: &mut CrashSummary) {
+        crash.path = PathBuf::from(self.redact(&crash.path.to_string_lossy()));
+        crash.description = crash.description.as_deref().map(|it| self.redact(it));
+        crash.exception = crash.exception.as_deref().map(|it| self.redact(it));
+        crash.jvm.flags = crash.jvm.flags.as_deref().map(|it| self.redact(it));
+        for line in &mut crash.stack_trace {
+            *line = self.redact(line);
+        }
+    }
+
+    pub fn redact_diagnosis(&self, diagnosis: &mut Diagnosis) {
+        diagnosis.diagnosis = self.redact(&diagnosis.diagnosis);
+        diagnosis.fix = self.redact(&diagnosis.fix);
+        diagnosis.evidence = self.redact(&diagnosis.evidence);
+    }
+}
+
+fn is_local_address(address: &str) -> bool {
+    address.starts_with("127.") || address == "0.0.0.0" || address == "0:0:0:0:0:0:0:1" || address == "0:0:0:0:0:0:0:0"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let secrets = KnownSecrets {
+            tokens: vec!["0.AXkAQ2lhbGxp-refresh".to_string()],
+            player_name: Some("Notch".to_string()),
+            player_uuid: Some("069a79f444e94726a5befca90e38aaf5".to_string()),
+            username: Some("alex".to_string()),
+            home: Some(PathBuf::from("/home/alex")),
+        };
+        let redactor = Redactor::new(&RedactionSettings::default(), &secrets);
+
+        let text = "[main/INFO]: Setting user: Notch
+--username Notch --uuid 069a79f444e94726a5befca90e38aaf5 --accessToken eyJhbGciOi.eyJzdWIiOi.c2lnbmF0dXJl
+refresh_token=0.AXkAQ2lhbGxp-refresh
+Loading /home/alex/.minecraft/mods, owned by alex
+Session 123e4567-e89b-12d3-a456-426614174000 connecting to 192.168.1.20:25565 or 127.0.0.1:25565";
+
+        assert_eq!(redactor.redact(text), "[main/INFO]: Setting user: <player>
+--username <player> --uuid <uuid> --accessToken <token>
+refresh_token=<token>
+Loading ~/.minecraft/mods, owned by <user>
+Session <uuid> connecting to <ip>:25565 or 127.0.0.1:25565");
+
+        let settings = RedactionSettings {
+            ip_addresses: false,
+            custom: vec![r"server-\d+".to_string()],
+            ..RedactionSettings::default()
+        };
+        let redactor = Redactor::new(&settings, &secrets);
+        assert_eq!(redactor.redact("server-42 at 10.0.0.1"), "<redacted> at 10.0.0.1");
+
+        let invalid = RedactionSettings {
+            custom: vec!["token=(".to_string()],
+            ..RedactionSettings::default()
+        };
+        assert!(invalid.validate().unwrap_err().contains("token=("));
+        assert!(settings.validate().is_ok());
+
+        let disabled = RedactionSettings {
+            enabled: false,
+            ..RedactionSettings::default()
+        };
+        assert_eq!(Redactor::new(&disabled, &secrets).redact(text), text);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::launch::minecraft::{Features, Rule};
+use regex::Regex;
+
+// Everything a rule in a version JSON can be checked against. Names follow Mojang's conventions
+// (`osx`, `x86`, ...) rather than Rust's.
+#[derive(Debug, Clone, Default)]
+pub struct RuleContext {
+    pub os_name: Option<String>,
+    pub os_arch: Option<String>,
+    pub os_version: Option<String>,
+    pub features: Features,
+}
+
+impl RuleContext {
+    pub fn current(features: Features) -> RuleContext {
+        let os_name = if cfg!(target_os = "windows") {
+            Some("windows".to_string())
+        } else if cfg!(target_os = "macos") {
+            Some("osx".to_string())
+        } else if cfg!(target_os = "linux") {
+            Some("linux".to_string())
+        } else if cfg!(target_os = "freebsd") {
+            Some("freebsd".to_string())
+        } else if cfg!(target_os = "dragonfly") {
+            Some("dragonfly".to_string())
+        } else if cfg!(target_os = "openbsd") {
+            Some("openbsd".to_string())
+        } else if cfg!(target_os = "netbsd") {
+            Some("netbsd".to_string())
+        } else if cfg!(target_os = "android") {
+            Some("android".to_string())
+        } else {
+            None
+        };
+
+        let os_arch = if cfg!(target_arch = "x86_64") {
+            Some("x86_64".to_string())
+        } else if cfg!(target_arch = "x86") {
+            Some("x86".to_string())
+        } else if cfg!(target_arch = "arm") {
+            Some("arm".to_string())
+        } else if cfg!(target_arch = "aarch64") {
+            Some("arm64".to_string())
+        } else if cfg!(target_arch = "mips") {
+            Some("mips".to_string())
+        } else if cfg!(target_arch = "mips64") {
+            Some("mips64".to_string())
+        } else {
+            None // Unsupported or unknown architecture
+        };
+
+        RuleContext {
+            os_name,
+            os_arch,
+            os_version: current_os_version(),
+            features,
+        }
+    }
+
+    // Mojang's semantics: with no rules everything is allowed, otherwise it starts out disallowed
+    // and the last rule that matches decides.
+    pub fn allows(&self, rules: &[Rule]) -> bool {
+        if rules.is_empty() {
+            return true;
+        }
+
+        rules
+            .iter()
+            .filter(|rule| self.matches(rule))
+            .last()
+            .is_some_and(|rule| rule.action == "allow")
+    }
+
+    fn matches(&self, rule: &Rule) -> bool {
+        if let Some(os) = &rule.os {
+            if os.name.is_some() && os.name != self.os_name {
+                return false;
+            }
+            if os.arch.is_some() && os.arch != self.os_arch {
+                return false;
+            }
+            if let Some(pattern) = &os.version {
+                let matches = Regex::new(pattern)
+                    .ok()
+                    .zip(self.os_version.as_ref())
+                    .is_some_and(|(regex, version)| regex.is_match(version));
+
+                if !matches {
+                    return false;
+                }
+            }
+        }
+
+        if let Some(features) = &rule.features {
+            // A feature we don't know about is treated as turned off
+            let required = [
+                (features.is_demo_user, self.features.is_demo_user),
+                (features.has_custom_resolution, self.features.has_custom_resolution),
+                (features.has_quick_plays_support, self.features.has_quick_plays_support),
+                (features.is_quick_play_singleplayer, self.features.is_quick_play_singleplayer),
+                (features.is_quick_play_multiplayer, self.features.is_quick_play_multiplayer),
+                (features.is_quick_play_realms, self.features.is_quick_play_realms),
+            ];
+
+            return required.iter().all(|(required, actual)| {
+                required.map_or(true, |required| required == actual.unwrap_or(false))
+            });
+        }
+
+        true
+    }
+}
+
+// What Java reports as `os.version`, which is what the regexes in version JSONs are written against
+fn current_os_version() -> Option<String> {
+    if cfg!(target_os = "linux") {
+        // The kernel release rather than the distribution's version
+        return std::fs::read_to_string("/proc/sys/kernel/osrelease")
+            .ok()
+            .map(|it| it.trim().to_string());
+    }
+
+    match os_info::get().version() {
+        os_info::Version::Unknown => None,
+        version => Some(version.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(name: &str, arch: &str, version: &str, features: Features) -> RuleContext {
+        RuleContext {
+            os_name: Some(name.to_string()),
+            os_arch: Some(arch.to_string()),
+            os_version: Some(version.to_string()),
+            features,
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        let demo = Features {
+            is_demo_user: Some(true),
+            ..Features::default()
+        };
+        let resolution = Features {
+            has_custom_resolution: Some(true),
+            ..Features::default()
+        };
+
+        // Rule sets taken from Mojang's version JSONs (1.7.10, 1.12.2, 1.16.5 and 1.21.1)
+        let cases = [
+            // lwjgl-platform natives, 1.12.2
+            (
+                r#"[{"action":"allow"},{"action":"disallow","os":{"name":"osx"}}]"#,
+                context("linux", "x86_64", "6.8.0", Features::default()),
+                true,
+            ),
+            (
+                r#"[{"action":"allow"},{"action":"disallow","os":{"name":"osx"}}]"#,
+                context("osx", "x86_64", "14.5", Features::default()),
+                false,
+            ),
+            // lwjgl 2.9.2-nightly, 1.12.2
+            (
+                r#"[{"action":"allow","os":{"name":"osx"}}]"#,
+                context("osx", "arm64", "14.5", Features::default()),
+                true,
+            ),
+            (
+                r#"[{"action":"allow","os":{"name":"osx"}}]"#,
+                context("windows", "x86_64", "10.0", Features::default()),
+                false,
+            ),
+            // lwjgl natives excluded on Leopard, 1.7.10
+            (
+                r#"[{"action":"allow"},{"action":"disallow","os":{"name":"osx","version":"^10\\.5\\.\\d$"}}]"#,
+                context("osx", "x86_64", "10.5.8", Features::default()),
+                false,
+            ),
+            (
+                r#"[{"action":"allow"},{"action":"disallow","os":{"name":"osx","version":"^10\\.5\\.\\d$"}}]"#,
+                context("osx", "x86_64", "10.15.7", Features::default()),
+                true,
+            ),
+            // -Dos.name=Windows 10, 1.16.5
+            (
+                r#"[{"action":"allow","os":{"name":"windows","version":"^10\\."}}]"#,
+                context("windows", "x86_64", "10.0.19045", Features::default()),
+                true,
+            ),
+            (
+                r#"[{"action":"allow","os":{"name":"windows","version":"^10\\."}}]"#,
+                context("windows", "x86_64", "6.1.7601", Features::default()),
+                false,
+            ),
+            // -Xss1M, 1.21.1
+            (
+                r#"[{"action":"allow","os":{"arch":"x86"}}]"#,
+                context("windows", "x86", "10.0", Features::default()),
+                true,
+            ),
+            (
+                r#"[{"action":"allow","os":{"arch":"x86"}}]"#,
+                context("windows", "x86_64", "10.0", Features::default()),
+                false,
+            ),
+            // --demo, 1.21.1
+            (
+                r#"[{"action":"allow","features":{"is_demo_user":true}}]"#,
+                context("linux", "x86_64", "6.8.0", demo.clone()),
+                true,
+            ),
+            (
+                r#"[{"action":"allow","features":{"is_demo_user":true}}]"#,
+                context("linux", "x86_64", "6.8.0", Features::default()),
+                false,
+            ),
+            // --width/--height, 1.21.1
+            (
+                r#"[{"action":"allow","features":{"has_custom_resolution":true}}]"#,
+                context("linux", "x86_64", "6.8.0", resolution.clone()),
+                true,
+            ),
+            (
+                r#"[{"action":"allow","features":{"has_custom_resolution":true}}]"#,
+                context("linux", "x86_64", "6.8.0", demo),
+                false,
+            ),
+            (r#"[]"#, context("linux", "x86_64", "6.8.0", Features::default()), true),
+        ];
+
+        for (rules, context, expected) in cases {
+            let rules: Vec<Rule> = serde_json::from_str(rules).unwrap();
+
+            assert_eq!(context.allows(&rules), expected, "{:?} with {:?}", rules, context);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::launch::minecraft::{
+    inherits_from, Artifact, AssetObjects, Error, MinecraftEnvironment, MAX_INHERITANCE_DEPTH,
+};
+use crate::launch::natives::{natives_intact, reextract_natives, NativeArtifact};
+use crate::task::download::DownloadScheduler;
+use crate::task::{Task, TaskManager};
+use crate::util::hash::sha1_bytes;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::{read, read_dir};
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Debug)]
+pub struct InstallationReport {
+    pub version: String,
+    pub checked: usize,
+    pub missing: Vec<Artifact>,
+    pub corrupt: Vec<Artifact>,
+    pub extra: Vec<PathBuf>,
+    // Whether the natives extracted for this version are all there, they aren't artifacts of their own
+    pub natives_intact: bool,
+    pub repaired: bool,
+    #[serde(skip)]
+    natives: Vec<NativeArtifact>,
+}
+
+impl InstallationReport {
+    pub fn is_healthy(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty() && self.natives_intact
+    }
+
+    fn check(&mut self, artifact: Artifact) -> io::Result<()> {
+        self.checked += 1;
+
+        if !artifact.path.exists() {
+            self.missing.push(artifact);
+        } else if !artifact.is_valid()? {
+            self.corrupt.push(artifact);
+        }
+
+        Ok(())
+    }
+
+    // The JSON files are needed to walk the rest of the installation, so if the copy on disk is
+    // broken the upstream one is read instead.
+    async fn check_json(
+        &mut self,
+        artifact: Artifact,
+        downloads: &DownloadScheduler,
+    ) -> Result<Vec<u8>, Error> {
+        self.checked += 1;
+
+        match read(&artifact.path) {
+            Ok(bytes) if Some(sha1_bytes(&bytes)) == artifact.sha1 => Ok(bytes),
+            existing => {
+                let bytes = artifact.fetch_bytes(downloads).await?.to_vec();
+
+                if existing.is_ok() {
+                    self.corrupt.push(artifact);
+                } else {
+                    self.missing.push(artifact);
+                }
+
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+// Walks every file the environment for this version depends on and reports anything missing or not
+// matching its expected hash. Nothing is written to disk.
+pub async fn verify_installation(
+    path: &Path,
+    version: &str,
+    downloads: &DownloadScheduler,
+) -> Result<InstallationReport, Error> {
+    let mut report = InstallationReport {
+        version: version.to_string(),
+        checked: 0,
+        missing: vec![],
+        corrupt: vec![],
+        extra: vec![],
+        natives_intact: true,
+        repaired: false,
+        natives: vec![],
+    };
+
+    let json_path = MinecraftEnvironment::version_json_path(path, version);
+
+    let mut chain = Vec::new();
+    let mut current = Some(version.to_string());
+    while let Some(version) = current {
+        // Without the manifest there is nothing to check the version JSON against, but everything
+        // else can still be checked from the copy on disk. Custom versions are never in it, so the
+        // manifest is only refreshed for a JSON that isn't there yet.
+        let version_json_path = MinecraftEnvironment::version_json_path(path, &version);
+        let artifact = if version_json_path.exists() {
+            MinecraftEnvironment::listed_version_json_artifact(path, &version, downloads).await
+        } else {
+            Some(MinecraftEnvironment::version_json_artifact(path, &version, downloads).await?)
+        };
+
+        let json = match artifact {
+            Some(artifact) => report.check_json(artifact, downloads).await?,
+            None => read(version_json_path)?,
+        };
+
+        let json: Value = serde_json::from_slice(&json).map_err(Error::Serde)?;
+        current = inherits_from(&json).filter(|_| chain.len() < MAX_INHERITANCE_DEPTH);
+        chain.push(json);
+    }
+
+    let info = MinecraftEnvironment::merge_version_info(chain)?;
+
+    let client_jar = MinecraftEnvironment::client_artifact(path, &info)?;
+    let expected_files = HashSet::from([json_path.clone(), client_jar.path.clone()]);
+
+    report.check(client_jar)?;
+
+    if let Some(logging) = MinecraftEnvironment::logging_artifact(path, &info) {
+        report.check(logging)?;
+    }
+
+    let natives = MinecraftEnvironment::native_artifacts(path, &info);
+
+    for artifact in MinecraftEnvironment::library_artifacts(path, &info)
+        .into_iter()
+        .chain(natives.iter().map(|native| native.artifact.clone())) {
+        report.check(artifact)?;
+    }
+
+    report.natives_intact = natives.is_empty() || natives_intact(path, version, &natives);
+    report.natives = natives;
+
+    let asset_index = report
+        .check_json(MinecraftEnvironment::asset_index_artifact(path, &info), downloads)
+        .await?;
+    let asset_index: AssetObjects = serde_json::from_slice(&asset_index).map_err(Error::Serde)?;
+
+    // Different asset names can point at the same object
+    let mut seen = HashSet::new();
+    for artifact in MinecraftEnvironment::asset_artifacts(path, &asset_index) {
+        if seen.insert(artifact.path.clone()) {
+            report.check(artifact)?;
+        }
+    }
+
+    if let Some(version_dir) = json_path.parent() {
+        if version_dir.exists() {
+            for entry in read_dir(version_dir)? {
+                let entry_path = entry?.path();
+
+                if !expected_files.contains(&entry_path) {
+                    report.extra.push(entry_path);
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+// Re-downloads only what the report found to be missing or corrupt, then extracts the natives again
+// if they or one of their jars were among it.
+pub async fn repair_installation(
+    path: &Path,
+    report: &InstallationReport,
+    tasks: &mut TaskManager,
+    downloads: &DownloadScheduler,
+) -> Result<(), Error> {
+    let requests = report.missing
+        .iter()
+        .chain(report.corrupt.iter())
+        .map(Artifact::request)
+        .collect::<Vec<_>>();
+
+    tasks.submit(format!("Repair Minecraft {}", report.version), |task: Task| {
+        downloads.download_all(requests, task.to_arc())
+    }).await?;
+
+    let natives_repaired = report.natives.iter().any(|native| {
+        report.missing.iter().chain(report.corrupt.iter()).any(|it| it.path == native.artifact.path)
+    });
+
+    if !report.natives_intact || natives_repaired {
+        reextract_natives(path, &report.version, &report.natives)?;
+    }
+
+    Ok(())
+}
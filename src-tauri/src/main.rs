@@ -2,13 +2,15 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use crate::extensions::{get_extension_state, get_maven_local, set_extension_state};
-use crate::launch::{end_launch_process, launch_minecraft};
+use crate::instances::{create_instance, delete_instance, get_instance, list_instances, update_instance};
+use crate::launch::{collect_garbage, dry_run_launch, end_launch_process, export_diagnostic_bundle, focus_process, get_minecraft_versions, launch_minecraft, list_processes, verify_minecraft_installation};
 use crate::mods::{get_mod_state, set_mod_state};
 use crate::oauth::{do_ms_refresh, get_mc_profile, logout, microsoft_login, use_no_auth};
 use crate::open_url::open_url;
 use crate::persist::PersistedData;
-use crate::state::{Extension, LaunchInstance, MinecraftAuthentication, OAuthConfig};
+use crate::state::{Extension, MinecraftAuthentication, OAuthConfig, ProcessRegistry};
 use crate::task::channel_progress::{register_task_channel, ChannelProgressBuilder, ChannelProgressManager};
+use crate::task::download::DownloadScheduler;
 use crate::task::TaskManager;
 use discord_rich_presence::activity::Timestamps;
 use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
@@ -23,10 +25,11 @@ use std::{env, io};
 use tauri::ipc::Channel;
 use tauri::{AppHandle, Manager};
 use tokio::sync::{Mutex, MutexGuard};
-use crate::launch::logs::export_logs;
-use crate::settings::{get_settings, save_settings, DebuggerSettings, UserSettings};
+use crate::launch::logs::{export_logs, list_session_logs, read_session_log, search_session_logs, tail_session_log};
+use crate::settings::{get_memory_info, get_mirror_status, get_settings, save_settings, DebuggerSettings, DownloadSettings, JvmSettings, LoggingSettings, UserSettings};
 
 mod extensions;
+mod instances;
 mod launch;
 mod mods;
 mod oauth;
@@ -85,7 +88,7 @@ fn main() {
             scope: "XboxLive.signin%20offline_access".to_string(),
             tenant: "consumers".to_string(),
         })
-        .manage(Arc::new(Mutex::new(None::<LaunchInstance>)))
+        .manage(ProcessRegistry::default())
         .manage(std::sync::Mutex::new(discord_client.ok()))
         .manage(
             PersistedData::read_from(yakclient_dir().join("config.json"))
@@ -94,11 +97,23 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             microsoft_login,
             launch_minecraft,
+            dry_run_launch,
+            export_diagnostic_bundle,
+            collect_garbage,
             end_launch_process,
+            list_processes,
+            focus_process,
+            verify_minecraft_installation,
+            get_minecraft_versions,
             set_extension_state,
             get_extension_state,
             set_mod_state,
             get_mod_state,
+            list_instances,
+            get_instance,
+            create_instance,
+            update_instance,
+            delete_instance,
             use_no_auth,
             open_url,
             get_mc_profile,
@@ -107,7 +122,13 @@ fn main() {
             register_task_channel,
             get_settings,
             save_settings,
+            get_mirror_status,
+            get_memory_info,
             export_logs,
+            list_session_logs,
+            read_session_log,
+            tail_session_log,
+            search_session_logs,
             do_ms_refresh,
             logout
         ])
@@ -139,9 +160,15 @@ fn main() {
                         suspend: true,
                         port: "5050".to_string(),
                     },
+                    downloads: DownloadSettings::default(),
+                    logging: LoggingSettings::default(),
+                    jvm: JvmSettings::default(),
                 });
             }
 
+            let settings: UserSettings = persisted_data.read_value("settings").unwrap();
+            app.manage(DownloadScheduler::new(&settings.downloads));
+
             Ok(())
         })
         .on_window_event(|app_handle, event| {
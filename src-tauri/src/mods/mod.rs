@@ -2,6 +2,7 @@ use crate::extensions::types::{
     ExtensionParent, ExtensionRepository, ExtensionRuntimeModel,
     PartitionRuntimeModel,
 };
+use crate::instances::{find_instance, modify_instance, InstanceError};
 use crate::persist::PersistedData;
 use crate::state::{Extension, Mod, RepositoryType};
 use futures::stream::iter;
@@ -11,26 +12,33 @@ use std::collections::{HashMap, HashSet};
 use std::fmt::{format, Display, Formatter};
 use std::fs::{copy, create_dir_all, File};
 use std::io::{Cursor, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use home::home_dir;
 use tauri::State;
 use uuid::Uuid;
 use rand::{random, thread_rng, Rng};
+use crate::task::download::DownloadScheduler;
 use crate::util::rand::generate_random_id;
 
+const GENERATED_GROUP: &str = "dev.extframework.generated";
+
 #[tauri::command]
 pub async fn set_mod_state(
     updated: Vec<Mod>,
+    instance: Option<String>,
     persisted_data: State<'_, PersistedData>,
-) -> Result<(), ()> {
-    persisted_data.put_value("mods", updated);
+) -> Result<(), InstanceError> {
+    modify_instance(&persisted_data, instance.as_deref(), |it| it.mods = updated)?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_mod_state(persisted_data: State<'_, PersistedData>) -> Result<Vec<Mod>, ()> {
-    Ok(persisted_data.read_value("mods").unwrap_or(Vec::new()))
+pub async fn get_mod_state(
+    instance: Option<String>,
+    persisted_data: State<'_, PersistedData>,
+) -> Result<Vec<Mod>, InstanceError> {
+    Ok(find_instance(&persisted_data, instance.as_deref())?.mods)
 }
 
 #[derive(Debug)]
@@ -93,59 +101,107 @@ impl ModsStore {
 pub async fn get_mod_extension(
     mods: &Vec<Mod>,
     path: PathBuf,
+    downloads: &DownloadScheduler,
 ) -> Result<Extension, ModExtGenerationError> {
-    let mods_lookup_path = path.join("mods.json");
-
-    let mut store: ModsStore = if !mods_lookup_path.exists() {
-        create_dir_all(mods_lookup_path.parent().unwrap())
-            .map_err(ModExtGenerationError::IOError)?;
-
-        ModsStore {
-            lookup: HashMap::new(),
-        }
-    } else {
-        let file = File::open(&mods_lookup_path)
-            .map_err(ModExtGenerationError::IOError)?;
-
-        serde_json::from_reader(
-            file
-        ).map_err(ModExtGenerationError::SerdeError)?
-    };
+    let mut store = read_mods_store(&path)?;
 
     if let Some(extension) = store.lookup(&mods) {
         Ok(extension.clone())
     } else {
         let generated: Extension = generate_mod_extension(
             &mods,
-            path,
+            path.clone(),
+            downloads,
         ).await?;
 
         store.put(&mods, generated.clone());
+        write_mods_store(&path, &store)?;
 
-        let file = File::create(mods_lookup_path)
-            .map_err(ModExtGenerationError::IOError)?;
+        Ok(generated)
+    }
+}
 
-        serde_json::to_writer(file, &store)
-            .map_err(ModExtGenerationError::SerdeError)?;
+fn read_mods_store(path: &Path) -> Result<ModsStore, ModExtGenerationError> {
+    let mods_lookup_path = path.join("mods.json");
 
-        Ok(generated)
+    if !mods_lookup_path.exists() {
+        create_dir_all(path).map_err(ModExtGenerationError::IOError)?;
+
+        return Ok(ModsStore {
+            lookup: HashMap::new(),
+        });
     }
+
+    let file = File::open(&mods_lookup_path)
+        .map_err(ModExtGenerationError::IOError)?;
+
+    serde_json::from_reader(
+        file
+    ).map_err(ModExtGenerationError::SerdeError)
+}
+
+fn write_mods_store(path: &Path, store: &ModsStore) -> Result<(), ModExtGenerationError> {
+    let file = File::create(path.join("mods.json"))
+        .map_err(ModExtGenerationError::IOError)?;
+
+    serde_json::to_writer(file, store)
+        .map_err(ModExtGenerationError::SerdeError)
+}
+
+// Where every generated extension ends up, one directory per extension
+pub fn generated_mods_dir(path: &Path) -> PathBuf {
+    path.join(GENERATED_GROUP.replace(".", std::path::MAIN_SEPARATOR_STR))
+}
+
+// The extension generated for exactly these mods, if there is one
+pub fn stored_mod_extension(
+    path: &Path,
+    mods: &Vec<Mod>,
+) -> Result<Option<Extension>, ModExtGenerationError> {
+    Ok(read_mods_store(path)?.lookup(mods).cloned())
+}
+
+// Names of the generated extensions still used by one of these mod lists
+pub fn reachable_mod_extensions(
+    path: &Path,
+    mod_sets: &[Vec<Mod>],
+) -> Result<HashSet<String>, ModExtGenerationError> {
+    let store = read_mods_store(path)?;
+
+    Ok(mod_sets
+        .iter()
+        .filter_map(|mods| store.lookup(mods))
+        .filter_map(|extension| extension.descriptor.split(':').nth(1).map(str::to_string))
+        .collect())
+}
+
+// Drops every generated extension the store knows about except the ones named
+pub fn forget_mod_extensions(
+    path: &Path,
+    keep: &HashSet<String>,
+) -> Result<(), ModExtGenerationError> {
+    let mut store = read_mods_store(path)?;
+
+    store.lookup.retain(|_, extension| {
+        extension.descriptor.split(':').nth(1).is_some_and(|name| keep.contains(name))
+    });
+
+    write_mods_store(path, &store)
 }
 
 pub async fn generate_mod_extension(
     mods: &Vec<Mod>,
     path: PathBuf,
+    downloads: &DownloadScheduler,
 ) -> Result<Extension, ModExtGenerationError> {
-    let client = reqwest::Client::new();
     let requested_loaders = mods.iter().map(|t| t.loader.clone()).collect();
 
     let mods = mods.iter().map(|it| async {
-        let response = client
-            .get(format!(
+        let response = downloads
+            .get(&format!(
                 "https://api.modrinth.com/v2/project/{}/version",
                 it.project_id
             ))
-            .send()
             .await
             .map_err(|e| ModExtGenerationError::NetworkError(e))?;
         let bytes = response
@@ -213,7 +269,7 @@ pub async fn generate_mod_extension(
 
     let runtime_model = ExtensionRuntimeModel {
         api_version: 2,
-        group_id: "dev.extframework.generated".to_string(),
+        group_id: GENERATED_GROUP.to_string(),
         name: format!("mods-{}", generate_random_id(8)),
         version: "1".to_string(),
         repositories: vec![HashMap::from([(
@@ -286,6 +342,7 @@ pub async fn generate_mod_extension(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::settings::DownloadSettings;
 
     #[tokio::test]
     async fn test_empty_mod_ext_creation() {
@@ -301,6 +358,7 @@ mod tests {
                 },
             ],
             PathBuf::from("tests/repo"),
+            &DownloadScheduler::new(&DownloadSettings::default()),
         )
             .await
             .unwrap();
@@ -313,6 +371,7 @@ mod tests {
 
             ],
             PathBuf::from("tests/repo"),
+            &DownloadScheduler::new(&DownloadSettings::default()),
         )
             .await
             .unwrap();
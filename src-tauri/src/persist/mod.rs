@@ -42,11 +42,44 @@ impl PersistedData {
         }
     }
 
+    // Reads, changes and writes back a value under one lock, so updates running at the same time
+    // can't undo each other. Nothing is written back if `update` fails, and `None` removes the value.
+    pub fn update_value<T, R, E, S: AsRef<str>>(
+        &self,
+        name: S,
+        update: impl FnOnce(&mut Option<T>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let mut content = self.content.lock().unwrap();
+        let mut value = content
+            .get(name.as_ref())
+            .map(|it| serde_json::from_value(it.clone()).expect("Failed to deserialize"));
+
+        let result = update(&mut value)?;
+
+        match value {
+            Some(value) => content.insert(
+                name.as_ref().to_string(),
+                serde_json::to_value(&value).expect("Failed to serialize"),
+            ),
+            None => content.remove(name.as_ref()),
+        };
+
+        Ok(result)
+    }
+
     pub fn remove_value<S: AsRef<str>>(&self, name: S) -> bool
     {
         self.content.lock().unwrap().remove(name.as_ref()).is_some()
     }
 
+    // Everything stored, as it would be written to disk
+    pub fn snapshot(&self) -> HashMap<String, Value> {
+        self.content.lock().unwrap().clone()
+    }
+
     pub fn persist_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
         let path = path.as_ref();
 
@@ -89,6 +122,23 @@ mod tests {
         assert_eq!(read, Some(expected_ret));
     }
 
+    #[test]
+    fn test_update_value() {
+        let data = PersistedData::new_empty();
+        data.put_value("count", 1);
+
+        let result: Result<(), ()> = data.update_value("count", |it: &mut Option<i32>| {
+            *it = Some(it.unwrap() + 1);
+            Err(())
+        });
+        assert!(result.is_err());
+        assert_eq!(data.read_value::<i32, _>("count"), Some(1));
+
+        let previous = data.update_value("count", |it: &mut Option<i32>| Ok::<_, ()>(it.take()));
+        assert_eq!(previous, Ok(Some(1)));
+        assert!(data.read_value::<i32, _>("count").is_none());
+    }
+
     #[test]
     fn test_persist() {
         let mut data = PersistedData::new_empty();
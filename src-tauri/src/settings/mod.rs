@@ -1,11 +1,23 @@
+use std::collections::HashMap;
 use std::sync::Mutex;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tauri::State;
+use crate::instances::InstanceJvmSettings;
+use crate::launch::jvm::{suggested_max_memory, total_memory, JvmOptions};
 use crate::persist::PersistedData;
+use crate::task::download::DownloadScheduler;
+use crate::task::mirrors::{MirrorSource, MirrorStatus};
 
 #[derive(Serialize,Deserialize,Clone)]
 pub struct UserSettings {
-    pub debugger: DebuggerSettings
+    pub debugger: DebuggerSettings,
+    #[serde(default)]
+    pub downloads: DownloadSettings,
+    #[serde(default)]
+    pub logging: LoggingSettings,
+    #[serde(default)]
+    pub jvm: JvmSettings,
 }
 
 #[derive(Serialize,Deserialize, Clone)]
@@ -15,6 +27,124 @@ pub struct DebuggerSettings {
     pub port: String
 }
 
+#[derive(Serialize,Deserialize, Clone)]
+pub struct DownloadSettings {
+    pub parallelism: usize,
+    pub max_attempts: u32,
+    // Base URLs tried in order before the upstream one
+    #[serde(default)]
+    pub mirrors: HashMap<MirrorSource, Vec<String>>,
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        DownloadSettings {
+            parallelism: 16,
+            max_attempts: 5,
+            mirrors: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize,Deserialize, Clone)]
+#[serde(default)]
+pub struct LoggingSettings {
+    // Swaps the version's Log4j config for ours, which writes every event as an XML record
+    pub launcher_config: bool,
+    // How many session logs are kept per instance, and how many bytes they can take up together
+    pub max_sessions: usize,
+    pub max_size: u64,
+    pub redaction: RedactionSettings,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        LoggingSettings {
+            launcher_config: false,
+            max_sessions: 20,
+            max_size: 100 * 1024 * 1024,
+            redaction: RedactionSettings::default(),
+        }
+    }
+}
+
+// What is masked in exported logs and crash details. Each kind can be turned off on its own.
+#[derive(Serialize,Deserialize, Clone)]
+#[serde(default)]
+pub struct RedactionSettings {
+    pub enabled: bool,
+    // Also masks what the launcher shows, the live console and past session logs
+    pub display: bool,
+    pub tokens: bool,
+    pub uuids: bool,
+    pub player: bool,
+    pub username: bool,
+    pub home_paths: bool,
+    pub ip_addresses: bool,
+    // Extra regular expressions, whatever they match is replaced with `<redacted>`
+    pub custom: Vec<String>,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        RedactionSettings {
+            enabled: true,
+            display: false,
+            tokens: true,
+            uuids: true,
+            player: true,
+            username: true,
+            home_paths: true,
+            ip_addresses: true,
+            custom: vec![],
+        }
+    }
+}
+
+impl RedactionSettings {
+    // A pattern that doesn't compile would leave whatever it was meant to hide in exported logs
+    pub fn validate(&self) -> Result<(), String> {
+        for pattern in &self.custom {
+            Regex::new(pattern)
+                .map_err(|e| format!("The redaction pattern '{}' isn't a valid regular expression: {}", pattern, e))?;
+        }
+
+        Ok(())
+    }
+}
+
+// How the game's JVM is started. Instances can override each of these.
+#[derive(Serialize,Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct JvmSettings {
+    // In megabytes. Without a maximum, one is suggested from how much memory the system has.
+    pub min_memory: Option<u32>,
+    pub max_memory: Option<u32>,
+    pub preset: JvmPreset,
+    // Added after the version's own JVM arguments and the preset's flags
+    pub args: Vec<String>,
+    // Added after the version's game arguments
+    pub game_args: Vec<String>,
+}
+
+// Garbage collector flags that are known to work well for the game
+#[derive(Serialize,Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum JvmPreset {
+    #[default]
+    None,
+    G1,
+    Zgc,
+    Aikar,
+}
+
+#[derive(Serialize)]
+pub struct MemoryInfo {
+    // In megabytes, missing when it couldn't be found out
+    pub total: Option<u64>,
+    pub suggested_max: u32,
+}
+
 #[tauri::command]
 pub fn get_settings(
     persisted_data: State<'_, PersistedData>
@@ -25,7 +155,32 @@ pub fn get_settings(
 #[tauri::command]
 pub fn save_settings(
     settings: UserSettings,
-    persisted_data: State<'_, PersistedData>
-) {
+    persisted_data: State<'_, PersistedData>,
+    downloads: State<'_, DownloadScheduler>,
+) -> Result<(), String> {
+    let jvm = JvmOptions::resolve(&settings.jvm, &InstanceJvmSettings::default(), None);
+    jvm.validate(None, None)?;
+    settings.logging.redaction.validate()?;
+
+    downloads.configure(&settings.downloads);
     persisted_data.put_value("settings", settings);
-}
\ No newline at end of file
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_memory_info() -> MemoryInfo {
+    let total = total_memory();
+
+    MemoryInfo {
+        total,
+        suggested_max: suggested_max_memory(total),
+    }
+}
+
+#[tauri::command]
+pub fn get_mirror_status(
+    downloads: State<'_, DownloadScheduler>,
+) -> Vec<MirrorStatus> {
+    downloads.mirrors().status()
+}
@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+use std::io;
 use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
@@ -26,16 +29,110 @@ pub struct MinecraftProfile {
 }
 
 pub struct LaunchInstance {
+    pub id: String,
+    // The instance this process was launched from
+    pub instance_id: String,
+    pub version: String,
+    pub pid: u32,
+    // Milliseconds since the epoch
+    pub started_at: u64,
     pub child: Arc<Mutex<Child>>,
+    // Set once the launcher kills the process, so its exit isn't taken for a crash
+    pub stop_requested: Arc<AtomicBool>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ProcessInfo {
+    pub id: String,
+    pub instance_id: String,
+    pub version: String,
+    pub pid: u32,
+    pub started_at: u64,
 }
 
 impl LaunchInstance {
-    pub async fn shutdown(&self) {
-        self.child
+    pub async fn shutdown(&self) -> io::Result<()> {
+        self.stop_requested.store(true, Ordering::SeqCst);
+
+        match self.child.lock().await.kill() {
+            // It had already exited
+            Err(e) if e.kind() == io::ErrorKind::InvalidInput => Ok(()),
+            result => result,
+        }
+    }
+
+    pub async fn is_running(&self) -> bool {
+        matches!(self.child.lock().await.try_wait(), Ok(None))
+    }
+
+    pub fn info(&self) -> ProcessInfo {
+        ProcessInfo {
+            id: self.id.clone(),
+            instance_id: self.instance_id.clone(),
+            version: self.version.clone(),
+            pid: self.pid,
+            started_at: self.started_at,
+        }
+    }
+}
+
+// Every game process the launcher has started and that is still running, by id
+#[derive(Default)]
+pub struct ProcessRegistry {
+    processes: Mutex<HashMap<String, LaunchInstance>>,
+}
+
+impl ProcessRegistry {
+    pub async fn insert(&self, process: LaunchInstance) {
+        self.processes.lock().await.insert(process.id.clone(), process);
+    }
+
+    pub async fn remove(&self, id: &str) -> Option<LaunchInstance> {
+        self.processes.lock().await.remove(id)
+    }
+
+    pub async fn get(&self, id: &str) -> Option<ProcessInfo> {
+        self.prune().await;
+
+        self.processes.lock().await.get(id).map(LaunchInstance::info)
+    }
+
+    pub async fn list(&self) -> Vec<ProcessInfo> {
+        self.prune().await;
+
+        let mut processes = self.processes
             .lock()
             .await
-            .kill()
-            .expect("Failed to kill process");
+            .values()
+            .map(LaunchInstance::info)
+            .collect::<Vec<_>>();
+        processes.sort_by_key(|it| it.started_at);
+
+        processes
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.list().await.is_empty()
+    }
+
+    pub async fn is_instance_running(&self, instance_id: &str) -> bool {
+        self.list().await.iter().any(|it| it.instance_id == instance_id)
+    }
+
+    // Processes that exited on their own are dropped here
+    async fn prune(&self) {
+        let mut processes = self.processes.lock().await;
+
+        let mut exited = Vec::new();
+        for (id, process) in processes.iter() {
+            if !process.is_running().await {
+                exited.push(id.clone());
+            }
+        }
+
+        for id in exited {
+            processes.remove(&id);
+        }
     }
 }
 
@@ -66,3 +163,44 @@ pub struct Mod {
     pub project_id: String,
     pub loader: String,
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn process(id: &str, instance_id: &str, seconds: &str) -> LaunchInstance {
+        let child = Command::new("sleep").arg(seconds).spawn().unwrap();
+
+        LaunchInstance {
+            id: id.to_string(),
+            instance_id: instance_id.to_string(),
+            version: "1.21.4".to_string(),
+            pid: child.id(),
+            started_at: 0,
+            child: Arc::new(Mutex::new(child)),
+            stop_requested: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_registry() {
+        let registry = ProcessRegistry::default();
+        registry.insert(process("first", "a", "5")).await;
+        registry.insert(process("second", "b", "0")).await;
+
+        // Gives the second one time to exit on its own
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+        let running = registry.list().await;
+        assert_eq!(running.len(), 1);
+        assert_eq!(running[0].id, "first");
+        assert!(registry.is_instance_running("a").await);
+        assert!(!registry.is_instance_running("b").await);
+
+        let first = registry.remove("first").await.unwrap();
+        first.shutdown().await.unwrap();
+        assert!(first.stop_requested.load(Ordering::SeqCst));
+        assert!(registry.is_empty().await);
+    }
+}
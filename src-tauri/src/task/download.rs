@@ -1,15 +1,527 @@
-use crate::task::Task;
+use crate::settings::DownloadSettings;
+use crate::task::mirrors::Mirrors;
+use crate::task::{Progress, Task};
+use crate::util::hash::{sha1_file, verify_file};
+use futures::future::join_all;
+use futures::StreamExt;
+use reqwest::header::RANGE;
+use reqwest::{Client, Response, StatusCode};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs::{create_dir_all, remove_file, rename, OpenOptions};
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
 
-pub struct DownloadTask {
-    task: Task,
+// Delay before the first retry, doubled for every one after that
+const BASE_RETRY_DELAY: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct DownloadRequest {
+    pub name: String,
+    pub url: String,
+    pub path: PathBuf,
+    pub size: Option<u64>,
+    pub sha1: Option<String>,
+}
+
+impl DownloadRequest {
+    // Without a hash, a file is trusted as long as it exists with the expected size
+    pub fn is_complete(&self) -> io::Result<bool> {
+        if let Some(sha1) = &self.sha1 {
+            return verify_file(&self.path, self.size, sha1);
+        }
+
+        match self.size {
+            Some(size) => Ok(self.path.exists() && self.path.metadata()?.len() == size),
+            None => Ok(self.path.exists()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DownloadError {
+    Network(reqwest::Error),
+    Status(StatusCode),
+    Io(io::Error),
+    SizeMismatch { expected: u64, actual: u64 },
+    ChecksumMismatch { expected: String, actual: String },
+}
+
+impl DownloadError {
+    // Whether trying again later has a chance of working
+    fn is_transient(&self) -> bool {
+        match self {
+            DownloadError::Network(e) => {
+                e.is_timeout() || e.is_connect() || e.is_request() || e.is_body()
+            }
+            DownloadError::Status(status) => {
+                status.is_server_error()
+                    || *status == StatusCode::REQUEST_TIMEOUT
+                    || *status == StatusCode::TOO_MANY_REQUESTS
+                    || *status == StatusCode::RANGE_NOT_SATISFIABLE
+            }
+            DownloadError::Io(_) => false,
+            DownloadError::SizeMismatch { .. } => true,
+            DownloadError::ChecksumMismatch { .. } => true,
+        }
+    }
+}
+
+impl From<reqwest::Error> for DownloadError {
+    fn from(value: reqwest::Error) -> Self {
+        DownloadError::Network(value)
+    }
+}
+
+impl From<io::Error> for DownloadError {
+    fn from(value: io::Error) -> Self {
+        DownloadError::Io(value)
+    }
+}
+
+impl Display for DownloadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            DownloadError::Network(e) => e.to_string(),
+            DownloadError::Status(status) => format!("Server responded with {}", status),
+            DownloadError::Io(e) => e.to_string(),
+            DownloadError::SizeMismatch { expected, actual } => {
+                format!("Size mismatch (expected {} bytes, got {})", expected, actual)
+            }
+            DownloadError::ChecksumMismatch { expected, actual } => {
+                format!("Checksum mismatch (expected {}, got {})", expected, actual)
+            }
+        };
+
+        write!(f, "{}", str)
+    }
+}
+
+#[derive(Debug)]
+pub struct DownloadFailure {
+    pub name: String,
+    pub url: String,
+    pub error: DownloadError,
+}
+
+impl Display for DownloadFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to download {} from {}: {}", self.name, self.url, self.error)
+    }
+}
+
+#[derive(Debug)]
+pub struct DownloadFailures(pub Vec<DownloadFailure>);
+
+impl From<DownloadFailure> for DownloadFailures {
+    fn from(value: DownloadFailure) -> Self {
+        DownloadFailures(vec![value])
+    }
+}
+
+impl Display for DownloadFailures {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let [failure] = self.0.as_slice() {
+            return write!(f, "{}", failure);
+        }
+
+        write!(f, "{} downloads failed:", self.0.len())?;
+        for failure in &self.0 {
+            write!(f, "\n{}", failure)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Every download in launch/ goes through here, so the number of connections stays bounded no
+// matter how many artifacts are requested at once.
+pub struct DownloadScheduler {
+    client: Client,
+    permits: Arc<Semaphore>,
+    mirrors: Mirrors,
+    settings: std::sync::Mutex<DownloadSettings>,
+    in_flight: std::sync::Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>,
+}
+
+impl DownloadScheduler {
+    pub fn new(settings: &DownloadSettings) -> DownloadScheduler {
+        let client = Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .read_timeout(Duration::from_secs(30))
+            .build()
+            .expect("Failed to build the download client");
+
+        DownloadScheduler {
+            client,
+            permits: Arc::new(Semaphore::new(settings.parallelism.max(1))),
+            mirrors: Mirrors::new(&settings.mirrors),
+            settings: std::sync::Mutex::new(settings.clone()),
+            in_flight: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn mirrors(&self) -> &Mirrors {
+        &self.mirrors
+    }
+
+    // A plain GET for things that aren't files on disk (manifests, API queries), failing over
+    // through the mirrors until one of them responds successfully.
+    pub async fn get(&self, url: &str) -> Result<Response, reqwest::Error> {
+        let mut candidates = self.mirrors.candidates(url).into_iter().peekable();
+
+        loop {
+            let candidate = candidates.next().expect("Upstream is always a candidate");
+            let result = self.client
+                .get(&candidate)
+                .send()
+                .await
+                .and_then(Response::error_for_status);
+
+            self.mirrors.report(&candidate, result.is_ok());
+
+            match result {
+                Err(e) if candidates.peek().is_some() => {
+                    println!("Failing over from {} ({})", candidate, e);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub fn configure(&self, settings: &DownloadSettings) {
+        let mut current = self.settings.lock().unwrap();
+
+        let old = current.parallelism.max(1);
+        let new = settings.parallelism.max(1);
+
+        if new > old {
+            self.permits.add_permits(new - old);
+        } else if new < old {
+            // Permits held by running downloads can't be taken away, so they are forgotten as
+            // they come back instead
+            let permits = Arc::clone(&self.permits);
+            tauri::async_runtime::spawn(async move {
+                if let Ok(permits) = permits.acquire_many_owned((old - new) as u32).await {
+                    permits.forget();
+                }
+            });
+        }
+
+        self.mirrors.configure(&settings.mirrors);
+        *current = settings.clone();
+    }
+
+    // Downloads the request unless it is already complete. Requests for the same destination run
+    // one at a time, so whichever comes second finds the file already there.
+    pub async fn download(
+        &self,
+        request: &DownloadRequest,
+        tracker: &mut Progress,
+    ) -> Result<(), DownloadFailure> {
+        let path_lock = Arc::clone(
+            self.in_flight
+                .lock()
+                .unwrap()
+                .entry(request.path.clone())
+                .or_default(),
+        );
+
+        let result = {
+            let _guard = path_lock.lock().await;
+            self.download_exclusive(request, tracker).await
+        };
+
+        let mut in_flight = self.in_flight.lock().unwrap();
+        // Only the map and this call are left holding it, so nobody else is waiting on the path
+        if Arc::strong_count(&path_lock) <= 2 {
+            in_flight.remove(&request.path);
+        }
+
+        result.map_err(|error| DownloadFailure {
+            name: request.name.clone(),
+            url: request.url.clone(),
+            error,
+        })
+    }
+
+    // Downloads every request with the task's progress shared between them by size. Requests for
+    // the same destination are only downloaded once, and every failure is reported instead of
+    // just the first.
+    pub async fn download_all(
+        &self,
+        requests: Vec<DownloadRequest>,
+        task: Arc<Mutex<Progress>>,
+    ) -> Result<(), DownloadFailures> {
+        let mut destinations = HashSet::new();
+        let requests = requests
+            .into_iter()
+            .filter(|request| destinations.insert(request.path.clone()))
+            .collect::<Vec<_>>();
+
+        let total_size = requests
+            .iter()
+            .map(|request| request.size.unwrap_or(0))
+            .sum::<u64>();
+        let count = requests.len();
+
+        let downloads = requests.iter().map(|request| {
+            let weight = if total_size == 0 {
+                1.0 / count as f64
+            } else {
+                request.size.unwrap_or(0) as f64 / total_size as f64
+            };
+            let mut tracker = Task::child(&task, weight);
+
+            async move { self.download(request, &mut tracker).await }
+        });
+
+        let failures = join_all(downloads)
+            .await
+            .into_iter()
+            .filter_map(Result::err)
+            .collect::<Vec<_>>();
+
+        if failures.is_empty() {
+            task.lock().await.update(1.0).await;
+
+            Ok(())
+        } else {
+            Err(DownloadFailures(failures))
+        }
+    }
+
+    async fn download_exclusive(
+        &self,
+        request: &DownloadRequest,
+        tracker: &mut Progress,
+    ) -> Result<(), DownloadError> {
+        if request.is_complete()? {
+            tracker.update(1.0).await;
+
+            return Ok(());
+        }
+
+        if let Some(parent) = request.path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        let max_attempts = self.settings.lock().unwrap().max_attempts.max(1);
+        let mut attempt = 1;
+
+        loop {
+            let result = {
+                let _permit = self.permits.acquire().await.expect("Download scheduler was closed");
+                self.attempt_mirrors(request, tracker).await
+            };
+
+            match result {
+                Ok(()) => {
+                    tracker.update(1.0).await;
+
+                    return Ok(());
+                }
+                Err(e) if attempt < max_attempts && e.is_transient() => {
+                    let delay = BASE_RETRY_DELAY
+                        .saturating_mul(2u32.saturating_pow(attempt - 1))
+                        .min(MAX_RETRY_DELAY)
+                        + Duration::from_millis(rand::random::<u64>() % 250);
+
+                    println!("Retrying {} in {:?} ({})", request.name, delay, e);
+                    tokio::time::sleep(delay).await;
+
+                    attempt += 1;
+                }
+                Err(e) => {
+                    tracker.erroneously_complete(&e).await;
+
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    // Tries each mirror once, in order of health. The error from upstream, which is always tried
+    // last, decides whether the whole round is worth retrying.
+    async fn attempt_mirrors(
+        &self,
+        request: &DownloadRequest,
+        tracker: &mut Progress,
+    ) -> Result<(), DownloadError> {
+        let mut candidates = self.mirrors.candidates(&request.url).into_iter().peekable();
+
+        loop {
+            let url = candidates.next().expect("Upstream is always a candidate");
+            let result = self.attempt(request, &url, tracker).await;
+
+            match result {
+                Ok(()) => {
+                    self.mirrors.report(&url, true);
+
+                    return Ok(());
+                }
+                // Problems on our end aren't the mirror's fault
+                Err(DownloadError::Io(e)) => return Err(DownloadError::Io(e)),
+                Err(e) => {
+                    self.mirrors.report(&url, false);
+
+                    if candidates.peek().is_none() {
+                        return Err(e);
+                    }
+                    println!("Failing over from {} ({})", url, e);
+                }
+            }
+        }
+    }
+
+    // Writes into a `.part` file next to the destination and only renames it into place once it
+    // passes the size and hash checks. Whatever is already in the `.part` file, whether from an
+    // earlier attempt or an earlier run of the launcher, is resumed with a Range request.
+    async fn attempt(
+        &self,
+        request: &DownloadRequest,
+        url: &str,
+        tracker: &mut Progress,
+    ) -> Result<(), DownloadError> {
+        let part_path = part_path(&request.path);
+
+        let mut offset = match part_path.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+        // Anything at or past the expected size can't be resumed into something valid
+        if request.size.is_some_and(|size| offset >= size) {
+            remove_file(&part_path)?;
+            offset = 0;
+        }
+
+        let mut builder = self.client.get(url);
+        if offset > 0 {
+            builder = builder.header(RANGE, format!("bytes={}-", offset));
+        }
+
+        let response = builder.send().await?;
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            // The server disagrees with what we have, so the next attempt starts over
+            remove_file(&part_path)?;
+            return Err(DownloadError::Status(response.status()));
+        }
+        if !response.status().is_success() {
+            return Err(DownloadError::Status(response.status()));
+        }
+
+        // Servers that ignore the Range header send the whole file back
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            offset = 0;
+        }
+
+        let size = request
+            .size
+            .or(response.content_length().map(|length| length + offset))
+            .unwrap_or(0);
+        let mut stream = response.bytes_stream();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(offset > 0)
+            .truncate(offset == 0)
+            .open(&part_path)?;
+        let mut written = offset;
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            file.write_all(&chunk)?;
+
+            written += chunk.len() as u64;
+            if size > 0 {
+                tracker.update((written as f64 / size as f64).min(1.0)).await;
+            }
+        }
+        file.flush()?;
+        drop(file);
+
+        if let Some(expected) = request.size {
+            if written != expected {
+                // A short file is just an interrupted download and gets resumed, a long one is junk
+                if written > expected {
+                    remove_file(&part_path)?;
+                }
+                return Err(DownloadError::SizeMismatch { expected, actual: written });
+            }
+        }
+
+        if let Some(expected) = &request.sha1 {
+            let actual = sha1_file(&part_path)?;
+            if actual != *expected {
+                remove_file(&part_path)?;
+                return Err(DownloadError::ChecksumMismatch {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+
+        rename(&part_path, &request.path)?;
+
+        Ok(())
+    }
+}
+
+pub fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+
+    path.with_file_name(name)
 }
 
-// impl DownloadTask {
-//     pub fn new(
-//         url: String,
-//         size: f64,
-//         sha1: String
-//     ) -> DownloadTask {
-//
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transient_errors() {
+        assert!(DownloadError::Status(StatusCode::SERVICE_UNAVAILABLE).is_transient());
+        assert!(DownloadError::Status(StatusCode::TOO_MANY_REQUESTS).is_transient());
+        assert!(!DownloadError::Status(StatusCode::NOT_FOUND).is_transient());
+        assert!(DownloadError::ChecksumMismatch {
+            expected: "a".to_string(),
+            actual: "b".to_string(),
+        }.is_transient());
+    }
+
+    #[test]
+    fn test_complete_request() {
+        create_dir_all("tests/download").unwrap();
+        let path = PathBuf::from("tests/download/complete.txt");
+        std::fs::write(&path, b"abc").unwrap();
+
+        let mut request = DownloadRequest {
+            name: "complete.txt".to_string(),
+            url: "https://example.com/complete.txt".to_string(),
+            path,
+            size: Some(3),
+            sha1: Some("a9993e364706816aba3e25717850c26c9cd0d89d".to_string()),
+        };
+        assert!(request.is_complete().unwrap());
+
+        request.sha1 = Some("0000000000000000000000000000000000000000".to_string());
+        assert!(!request.is_complete().unwrap());
+
+        request.sha1 = None;
+        request.size = Some(4);
+        assert!(!request.is_complete().unwrap());
+    }
+
+    #[test]
+    fn test_part_path() {
+        assert_eq!(
+            part_path(Path::new("libraries/a/b.jar")),
+            PathBuf::from("libraries/a/b.jar.part")
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+// A mirror that failed this many times in a row is tried after the healthy ones
+const UNHEALTHY_AFTER: u32 = 3;
+// How long an unhealthy mirror stays at the back of the line before it gets another chance
+const UNHEALTHY_COOLDOWN: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorSource {
+    Mojang,
+    Libraries,
+    Resources,
+    Azul,
+    ExtFramework,
+    Modrinth,
+}
+
+impl MirrorSource {
+    pub const ALL: [MirrorSource; 6] = [
+        MirrorSource::Mojang,
+        MirrorSource::Libraries,
+        MirrorSource::Resources,
+        MirrorSource::Azul,
+        MirrorSource::ExtFramework,
+        MirrorSource::Modrinth,
+    ];
+
+    // The hosts a source is normally served from. A mirror stands in for all of them, so it is
+    // expected to serve the same paths.
+    pub fn upstreams(&self) -> &'static [&'static str] {
+        match self {
+            MirrorSource::Mojang => &[
+                "https://launchermeta.mojang.com",
+                "https://piston-meta.mojang.com",
+                "https://piston-data.mojang.com",
+            ],
+            MirrorSource::Libraries => &["https://libraries.minecraft.net"],
+            MirrorSource::Resources => &["https://resources.download.minecraft.net"],
+            MirrorSource::Azul => &["https://api.azul.com", "https://cdn.azul.com"],
+            MirrorSource::ExtFramework => &["https://maven.extframework.dev"],
+            MirrorSource::Modrinth => &["https://api.modrinth.com"],
+        }
+    }
+
+    // Finds the source a URL belongs to, along with the upstream it starts with
+    pub fn of(url: &str) -> Option<(MirrorSource, &'static str)> {
+        MirrorSource::ALL.iter().find_map(|source| {
+            source
+                .upstreams()
+                .iter()
+                .find(|upstream| url.starts_with(*upstream))
+                .map(|upstream| (*source, *upstream))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct MirrorHealth {
+    successes: u64,
+    failures: u64,
+    consecutive_failures: u32,
+    last_failure: Option<SystemTime>,
+}
+
+impl MirrorHealth {
+    fn is_healthy(&self) -> bool {
+        if self.consecutive_failures < UNHEALTHY_AFTER {
+            return true;
+        }
+
+        self.last_failure
+            .and_then(|it| it.elapsed().ok())
+            .is_some_and(|elapsed| elapsed >= UNHEALTHY_COOLDOWN)
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MirrorStatus {
+    pub source: MirrorSource,
+    pub base: String,
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    pub healthy: bool,
+}
+
+// The configured mirrors for each source, along with how well every base URL has been doing.
+// Upstream is always the last resort, so a broken mirror can slow things down but never stop them.
+pub struct Mirrors {
+    bases: Mutex<HashMap<MirrorSource, Vec<String>>>,
+    health: Mutex<HashMap<String, (MirrorSource, MirrorHealth)>>,
+}
+
+impl Mirrors {
+    pub fn new(bases: &HashMap<MirrorSource, Vec<String>>) -> Mirrors {
+        let mirrors = Mirrors {
+            bases: Mutex::new(HashMap::new()),
+            health: Mutex::new(HashMap::new()),
+        };
+        mirrors.configure(bases);
+
+        mirrors
+    }
+
+    pub fn configure(&self, bases: &HashMap<MirrorSource, Vec<String>>) {
+        let bases = bases
+            .iter()
+            .map(|(source, bases)| {
+                let bases = bases
+                    .iter()
+                    .map(|base| base.trim().trim_end_matches('/').to_string())
+                    .filter(|base| !base.is_empty())
+                    .collect();
+
+                (*source, bases)
+            })
+            .collect();
+
+        *self.bases.lock().unwrap() = bases;
+    }
+
+    // Every URL the resource can be fetched from, best first. URLs that don't belong to a known
+    // source are only ever fetched from where they point.
+    pub fn candidates(&self, url: &str) -> Vec<String> {
+        let Some((source, upstream)) = MirrorSource::of(url) else {
+            return vec![url.to_string()];
+        };
+        let path = &url[upstream.len()..];
+
+        let mut mirrors = self.bases
+            .lock()
+            .unwrap()
+            .get(&source)
+            .cloned()
+            .unwrap_or_default();
+
+        let health = self.health.lock().unwrap();
+        // Stable, so mirrors keep their configured order within each group
+        mirrors.sort_by_key(|base| {
+            !health.get(base).map(|(_, health)| health.is_healthy()).unwrap_or(true)
+        });
+
+        mirrors
+            .into_iter()
+            .map(|base| format!("{}{}", base, path))
+            .chain(std::iter::once(url.to_string()))
+            .collect()
+    }
+
+    pub fn report(&self, url: &str, success: bool) {
+        let Some((source, base)) = self.base_of(url) else {
+            return;
+        };
+
+        let mut health = self.health.lock().unwrap();
+        let (_, health) = health.entry(base).or_insert((source, MirrorHealth::default()));
+
+        if success {
+            health.successes += 1;
+            health.consecutive_failures = 0;
+        } else {
+            health.failures += 1;
+            health.consecutive_failures += 1;
+            health.last_failure = Some(SystemTime::now());
+        }
+    }
+
+    pub fn status(&self) -> Vec<MirrorStatus> {
+        let health = self.health.lock().unwrap();
+
+        let mut status = health
+            .iter()
+            .map(|(base, (source, health))| MirrorStatus {
+                source: *source,
+                base: base.clone(),
+                successes: health.successes,
+                failures: health.failures,
+                consecutive_failures: health.consecutive_failures,
+                healthy: health.is_healthy(),
+            })
+            .collect::<Vec<_>>();
+        status.sort_by(|a, b| a.base.cmp(&b.base));
+
+        status
+    }
+
+    fn base_of(&self, url: &str) -> Option<(MirrorSource, String)> {
+        if let Some((source, upstream)) = MirrorSource::of(url) {
+            return Some((source, upstream.to_string()));
+        }
+
+        self.bases.lock().unwrap().iter().find_map(|(source, bases)| {
+            bases
+                .iter()
+                .find(|base| url.starts_with(base.as_str()))
+                .map(|base| (*source, base.clone()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirror_failover() {
+        let mirrors = Mirrors::new(&HashMap::from([(
+            MirrorSource::Resources,
+            vec![
+                "https://cache.example.com/resources/".to_string(),
+                "https://backup.example.com".to_string(),
+            ],
+        )]));
+
+        let url = "https://resources.download.minecraft.net/ab/abcdef";
+        assert_eq!(
+            mirrors.candidates(url),
+            vec![
+                "https://cache.example.com/resources/ab/abcdef".to_string(),
+                "https://backup.example.com/ab/abcdef".to_string(),
+                url.to_string(),
+            ]
+        );
+
+        for _ in 0..UNHEALTHY_AFTER {
+            mirrors.report("https://cache.example.com/resources/ab/abcdef", false);
+        }
+        assert_eq!(
+            mirrors.candidates(url)[0],
+            "https://backup.example.com/ab/abcdef".to_string()
+        );
+
+        let status = mirrors.status();
+        assert_eq!(status.len(), 1);
+        assert!(!status[0].healthy);
+
+        assert_eq!(
+            mirrors.candidates("https://example.com/file"),
+            vec!["https://example.com/file".to_string()]
+        );
+    }
+}
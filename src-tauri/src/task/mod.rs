@@ -7,7 +7,8 @@ use std::sync::Arc;
 
 pub mod channel_progress;
 pub mod copy;
-mod download;
+pub mod download;
+pub mod mirrors;
 
 pub struct TaskManager {
     pub progress_builder: Box<dyn TrackerBuilder>,
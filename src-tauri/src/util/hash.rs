@@ -0,0 +1,66 @@
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+pub fn sha1_bytes(bytes: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(bytes);
+
+    format!("{:x}", hasher.finalize())
+}
+
+pub fn sha1_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha1::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Zulu only publishes SHA-256 checksums
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// True if the file exists and matches the expected hash, and size if it is known.
+pub fn verify_file(path: &Path, size: Option<u64>, sha1: &str) -> io::Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    if let Some(size) = size {
+        if path.metadata()?.len() != size {
+            return Ok(false);
+        }
+    }
+
+    Ok(sha1_file(path)? == sha1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::hash::{sha1_bytes, sha256_file};
+    use std::fs::{create_dir_all, write};
+    use std::path::Path;
+
+    #[test]
+    fn test_sha1_bytes() {
+        assert_eq!(sha1_bytes(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    }
+
+    #[test]
+    fn test_sha256_file() {
+        let path = Path::new("tests/hash/abc.txt");
+        create_dir_all(path.parent().unwrap()).unwrap();
+        write(path, b"abc").unwrap();
+
+        assert_eq!(
+            sha256_file(path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
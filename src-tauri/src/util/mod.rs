@@ -1,5 +1,6 @@
 use std::future::Future;
 
+pub mod hash;
 pub mod rand;
 
 pub async fn map_async<T, E, O, Fut, Fun>(
@@ -0,0 +1,119 @@
+import React, {useEffect, useState} from "react";
+import {Button, Form, Modal} from "react-bootstrap";
+import {invoke} from "@tauri-apps/api/core";
+import {Instance} from "@/types";
+
+type VersionListing = {
+    id: string,
+    type: string,
+    releaseTime: string,
+    installed: boolean,
+    inheritsFrom: string | null,
+}
+
+// Creates a new instance, or edits `instance` when one is given
+const InstanceForm: React.FC<{
+    instance: Instance | null,
+    onSaved: (instance: Instance) => void,
+    onCancel: () => void,
+}> = ({instance, onSaved, onCancel}) => {
+    let [name, setName] = useState(instance?.name ?? "")
+    let [version, setVersion] = useState(instance?.version ?? "")
+    let [gameDir, setGameDir] = useState(instance?.game_dir ?? "")
+    let [showSnapshots, setShowSnapshots] = useState(false)
+    let [versions, setVersions] = useState<VersionListing[]>([])
+    let [error, setError] = useState<string | null>(null)
+
+    useEffect(() => {
+        invoke<VersionListing[]>("get_minecraft_versions", {
+            types: showSnapshots ? ["release", "snapshot"] : ["release"]
+        }).then((it) => {
+            setVersions(it)
+            if (version == "" && it.length > 0) setVersion(it[0].id)
+        }).catch((it) => setError(it.toString()))
+        // eslint-disable-next-line react-hooks/exhaustive-deps
+    }, [showSnapshots])
+
+    const save = () => {
+        let saved = instance == null
+            ? invoke<Instance>("create_instance", {
+                name: name,
+                version: version,
+                icon: null,
+                // Left empty the launcher picks a directory of its own
+                gameDir: gameDir.trim().length == 0 ? null : gameDir,
+            })
+            : invoke<Instance>("update_instance", {
+                instance: {
+                    ...instance,
+                    name: name,
+                    version: version,
+                    game_dir: gameDir,
+                }
+            })
+
+        saved.then(onSaved).catch((it) => setError(it.toString()))
+    }
+
+    return <>
+        <Modal.Header closeButton>
+            <Modal.Title>{instance == null ? "New instance" : `Edit ${instance.name}`}</Modal.Title>
+        </Modal.Header>
+        <Modal.Body>
+            {error ? <div className="text-danger">{error}</div> : <></>}
+            <Form onSubmit={(e) => {
+                e.preventDefault()
+                save()
+            }}>
+                <Form.Label column={false}>Name:</Form.Label>
+                <Form.Control
+                    onChange={(it) => setName(it.target.value)}
+                    value={name}
+                />
+                <Form.Label column={false}>Minecraft version:</Form.Label>
+                <Form.Select
+                    onChange={(it) => setVersion(it.target.value)}
+                    value={version}
+                >
+                    {
+                        // The current version stays selectable even when it isn't listed anymore
+                        versions.some((it) => it.id == version) || version == ""
+                            ? <></>
+                            : <option value={version}>{version}</option>
+                    }
+                    {versions.map((it) => <option key={it.id} value={it.id}>
+                        {it.id}{it.type == "release" ? "" : ` (${it.type})`}
+                    </option>)}
+                </Form.Select>
+                <Form.Check
+                    type="checkbox"
+                    label="Show snapshots"
+                    checked={showSnapshots}
+                    onChange={() => setShowSnapshots(!showSnapshots)}
+                />
+                <Form.Label column={false}>Game directory:</Form.Label>
+                <Form.Control
+                    onChange={(it) => setGameDir(it.target.value)}
+                    value={gameDir}
+                    placeholder={"Managed by the launcher"}
+                />
+            </Form>
+        </Modal.Body>
+        <Modal.Footer>
+            <Button variant="secondary" onClick={onCancel}>Cancel</Button>
+            <Button
+                variant="success"
+                disabled={
+                    name.trim().length == 0 || version.length == 0
+                    // Only new instances can leave it to the launcher
+                    || (instance != null && gameDir.trim().length == 0)
+                }
+                onClick={save}
+            >
+                {instance == null ? "Create" : "Save"}
+            </Button>
+        </Modal.Footer>
+    </>
+}
+
+export default InstanceForm
@@ -7,11 +7,13 @@ import React, {LegacyRef, MouseEventHandler, useEffect, useState} from "react";
 import {Alert, Button, ButtonGroup, Container, Dropdown, Modal} from "react-bootstrap";
 import BackgroundGradient from "@/components/bg_gradient";
 import {Channel, invoke} from "@tauri-apps/api/core";
-import {Alerts, ConsoleLine, useConsole} from "@/pages/_app";
+import {Alerts, ConsoleBatch, useConsole} from "@/pages/_app";
 import {useRouter} from "next/router";
 import Nav from "@/components/nav";
 import {listen} from "@tauri-apps/api/event";
 import Settings from "@/components/settings/settings_popup";
+import {Instance} from "@/types";
+import InstanceForm from "@/components/instance_form";
 
 // eslint-disable-next-line react/display-name
 const ProfileButton = React.forwardRef((
@@ -41,16 +43,29 @@ const LaunchLayout: React.FC<{
     pages: { name: string; content: React.ReactNode; }[],
 }> = ({pages}) => {
     let [page, setPage] = useState(0)
-    let [version, setVersion] = useState<string | null>(null)
+    let [instance, setInstance] = useState<Instance | null>(null)
+    let [instances, setInstances] = useState<Instance[]>([])
     let [uuid, setUuid] = useState("")
     let [settingsOpen, setSettingsOpen] = useState(false)
+    // The instance being edited, `null` while creating one
+    let [instanceForm, setInstanceForm] = useState<{ editing: Instance | null } | null>(null)
 
     const console = useConsole()
     const router = useRouter();
 
-    const versions = [
-        "1.21.4", "1.21.3", "1.8.9"
-    ]
+    // Selects `selected` once loaded, or the first instance without it
+    const loadInstances = (selected?: string) => {
+        invoke("list_instances")
+            .then((it) => {
+                let loaded = it as Instance[]
+                setInstances(loaded)
+                setInstance(loaded.find((it) => it.id == selected) ?? (loaded.length == 0 ? null : loaded[0]))
+            })
+    }
+
+    useEffect(() => {
+        loadInstances()
+    }, [])
 
     useEffect(() => {
         invoke("get_mc_profile")
@@ -70,6 +85,19 @@ const LaunchLayout: React.FC<{
                     >
                         <Settings/>
                     </Modal>
+                    <Modal
+                        show={instanceForm != null}
+                        onHide={() => setInstanceForm(null)}
+                    >
+                        {instanceForm && <InstanceForm
+                            instance={instanceForm.editing}
+                            onSaved={(saved) => {
+                                setInstanceForm(null)
+                                loadInstances(saved.id)
+                            }}
+                            onCancel={() => setInstanceForm(null)}
+                        />}
+                    </Modal>
                     <div
                         id={styles.profile}
                     >
@@ -144,13 +172,13 @@ const LaunchLayout: React.FC<{
                     }}>
                         <Dropdown as={ButtonGroup} size="lg">
                             <Button
-                                disabled={version == null}
+                                disabled={instance == null}
                                 variant="success"
                                 onClick={() => {
-                                    let channel = new Channel<ConsoleLine>();
+                                    let channel = new Channel<ConsoleBatch>();
                                     console.setChannel(channel)
                                     invoke("launch_minecraft", {
-                                        version: version,
+                                        instance: instance!.id,
                                         consoleChannel: channel
                                     }).catch((it) => {
                                         alert(
@@ -161,12 +189,13 @@ const LaunchLayout: React.FC<{
                                                 {it.toString()}
                                             </>
                                         )
-                                    }).then(() => {
+                                    }).then((process) => {
+                                        if (process) console.setProcess(process as string)
                                         router.push("/console")
                                     })
                                 }}
                             >
-                                Launch {version == null ? "" : version}
+                                Launch {instance == null ? "" : instance.name}
                             </Button>
 
                             <Dropdown.Toggle split variant="success" id="dropdown-split-basic"/>
@@ -176,10 +205,40 @@ const LaunchLayout: React.FC<{
                                 maxHeight: "20em",
                             }}>
                                 {
-                                    versions.map((v, index) => <Dropdown.Item key={index} onClick={() => {
-                                        setVersion(v)
-                                    }}>{v}</Dropdown.Item>)
+                                    instances.map((it) => <Dropdown.Item key={it.id} onClick={() => {
+                                        setInstance(it)
+                                    }}>{it.name} ({it.version})</Dropdown.Item>)
                                 }
+                                <Dropdown.Divider/>
+                                <Dropdown.Item onClick={() => {
+                                    setInstanceForm({editing: null})
+                                }}>New instance...</Dropdown.Item>
+                                <Dropdown.Item disabled={instance == null} onClick={() => {
+                                    setInstanceForm({editing: instance})
+                                }}>Edit {instance == null ? "instance" : instance.name}...</Dropdown.Item>
+                                <Dropdown.Item disabled={instance == null} onClick={() => {
+                                    invoke<string>("export_diagnostic_bundle", {
+                                        instance: instance!.id
+                                    }).then((path) => {
+                                        alert(
+                                            "success",
+                                            <>
+                                                <Alert.Heading>Diagnostic bundle saved</Alert.Heading>
+                                                <hr/>
+                                                {path}
+                                            </>
+                                        )
+                                    }).catch((it) => {
+                                        alert(
+                                            "danger",
+                                            <>
+                                                <Alert.Heading>Failed to export diagnostics</Alert.Heading>
+                                                <hr/>
+                                                {it.toString()}
+                                            </>
+                                        )
+                                    })
+                                }}>Export diagnostic bundle</Dropdown.Item>
                             </Dropdown.Menu>
                         </Dropdown>
                     </Container>
@@ -6,13 +6,56 @@ export interface UserSettings {
         enabled: boolean,
         suspend: boolean,
         port: string
+    },
+    downloads: {
+        parallelism: number,
+        max_attempts: number,
+        // Base URLs per source, tried in order before the upstream one
+        mirrors: { [source: string]: string[] }
+    },
+    logging: {
+        launcher_config: boolean,
+        // Session logs kept per instance, by count and by total size in bytes
+        max_sessions: number,
+        max_size: number,
+        // What is masked in exported logs and crash details, and optionally in the console
+        redaction: {
+            enabled: boolean,
+            display: boolean,
+            tokens: boolean,
+            uuids: boolean,
+            player: boolean,
+            username: boolean,
+            home_paths: boolean,
+            ip_addresses: boolean,
+            custom: string[]
+        }
+    },
+    jvm: {
+        // In megabytes, without a maximum one is suggested from the system's memory
+        min_memory: number | null,
+        max_memory: number | null,
+        preset: JvmPreset,
+        args: string[],
+        game_args: string[]
     }
 }
 
+export type JvmPreset = "none" | "g1" | "zgc" | "aikar"
+
+export interface MemoryInfo {
+    total: number | null,
+    suggested_max: number
+}
+
 export const loadSettings = async () => {
     return await invoke<UserSettings>("get_settings")
 }
 
+export const loadMemoryInfo = async () => {
+    return await invoke<MemoryInfo>("get_memory_info")
+}
+
 export const saveSettings = async (settings: UserSettings) => {
     return await invoke("save_settings", {
         settings: settings
@@ -1,7 +1,14 @@
 import React, {useEffect, useState} from "react";
 import styles from "./settings_popup.module.sass"
 import {Button, Form} from "react-bootstrap";
-import {loadSettings, saveSettings, UserSettings} from "@/components/settings/settings";
+import {
+    JvmPreset,
+    loadMemoryInfo,
+    loadSettings,
+    MemoryInfo,
+    saveSettings,
+    UserSettings
+} from "@/components/settings/settings";
 
 
 
@@ -11,25 +18,81 @@ const Settings: React.FC = () => {
             enabled: false,
             suspend: false,
             port: ""
+        },
+        downloads: {
+            parallelism: 16,
+            max_attempts: 5,
+            mirrors: {}
+        },
+        logging: {
+            launcher_config: false,
+            max_sessions: 20,
+            max_size: 100 * 1024 * 1024,
+            redaction: {
+                enabled: true,
+                display: false,
+                tokens: true,
+                uuids: true,
+                player: true,
+                username: true,
+                home_paths: true,
+                ip_addresses: true,
+                custom: []
+            }
+        },
+        jvm: {
+            min_memory: null,
+            max_memory: null,
+            preset: "none",
+            args: [],
+            game_args: []
         }
     })
+    let [memory, setMemory] = useState<MemoryInfo | null>(null)
+    let [error, setError] = useState<string | null>(null)
+    // Kept as typed, blank lines are only dropped from what's saved
+    let [argsText, setArgsText] = useState("")
+    let [gameArgsText, setGameArgsText] = useState("")
 
     useEffect(() => {
         loadSettings().then((it) => {
             console.log(it)
             setSettings(it)
+            setArgsText(it.jvm.args.join("\n"))
+            setGameArgsText(it.jvm.game_args.join("\n"))
         })
     }, [])
 
     useEffect(() => {
-        saveSettings(settings as UserSettings).then(() => {
+        loadMemoryInfo().then(setMemory)
+    }, [])
 
+    useEffect(() => {
+        saveSettings(settings as UserSettings).then(() => {
+            setError(null)
+        }).catch((it) => {
+            setError(it.toString())
         })
     }, [settings])
 
+    const setJvm = (jvm: Partial<UserSettings["jvm"]>) => {
+        setSettings({
+            ...settings,
+            jvm: {
+                ...settings.jvm,
+                ...jvm
+            }
+        })
+    }
+
+    // One argument per line, so arguments with spaces survive
+    const parseArgs = (text: string) => text.split("\n").filter((it) => it.trim().length > 0)
+    const parseMemory = (text: string) => text.trim().length == 0 ? null : parseInt(text)
+
 
     return <div id={styles.container}>
         <h1>Settings</h1>
+        {error ? <div className="text-danger">{error}</div> : <></>}
 
         <div className={styles.section}>
             <h2>General</h2>
@@ -37,12 +100,62 @@ const Settings: React.FC = () => {
             <div>Nothing here right now..</div>
         </div>
 
+        <div className={styles.section}>
+            <h2>Java</h2>
+            <form>
+                <Form.Label column={false}>Minimum memory (MB):</Form.Label>
+                <Form.Control
+                    type="number"
+                    onChange={(it) => setJvm({min_memory: parseMemory(it.target.value)})}
+                    value={settings.jvm.min_memory ?? ""}
+                />
+                <Form.Label column={false}>
+                    Maximum memory (MB){memory?.total ? `, ${memory.total} MB installed` : ""}:
+                </Form.Label>
+                <Form.Control
+                    type="number"
+                    onChange={(it) => setJvm({max_memory: parseMemory(it.target.value)})}
+                    value={settings.jvm.max_memory ?? ""}
+                    placeholder={memory ? memory.suggested_max.toString() : ""}
+                />
+                <Form.Label column={false}>Garbage collector preset:</Form.Label>
+                <Form.Select
+                    onChange={(it) => setJvm({preset: it.target.value as JvmPreset})}
+                    value={settings.jvm.preset}
+                >
+                    <option value="none">Java&apos;s default</option>
+                    <option value="g1">G1</option>
+                    <option value="zgc">ZGC (Java 15+)</option>
+                    <option value="aikar">Aikar&apos;s flags</option>
+                </Form.Select>
+                <Form.Label column={false}>JVM arguments, one per line:</Form.Label>
+                <Form.Control
+                    as="textarea"
+                    onChange={(it) => {
+                        setArgsText(it.target.value)
+                        setJvm({args: parseArgs(it.target.value)})
+                    }}
+                    value={argsText}
+                />
+                <Form.Label column={false}>Game arguments, one per line:</Form.Label>
+                <Form.Control
+                    as="textarea"
+                    onChange={(it) => {
+                        setGameArgsText(it.target.value)
+                        setJvm({game_args: parseArgs(it.target.value)})
+                    }}
+                    value={gameArgsText}
+                />
+            </form>
+        </div>
+
         <div className={styles.section}>
             <h2>Advanced</h2>
             <div className={styles.section}>
                 <Button
                     onClick={() => {
                         setSettings({
+                            ...settings,
                             debugger: {
                                 ...settings.debugger,
                                 enabled: !settings.debugger.enabled,
@@ -58,6 +171,7 @@ const Settings: React.FC = () => {
                         <Button
                             onClick={() => {
                                 setSettings({
+                                    ...settings,
                                     debugger: {
                                         ...settings.debugger,
                                         suspend: !settings.debugger.suspend,
@@ -73,6 +187,7 @@ const Settings: React.FC = () => {
                             <Form.Control
                                 onChange={(it) => {
                                     setSettings({
+                                        ...settings,
                                         debugger: {
                                             ...settings.debugger,
                                             port: it.target.value.length == 0 ? "5050" : it.target.value
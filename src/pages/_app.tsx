@@ -24,18 +24,32 @@ export const Alerts = React.createContext<AddAlert>(() => {});
 // ---- CONSOLE LOG SYSTEM ----
 
 export type ConsoleLine = {
-    is_err: string,
-    frag: number[]
+    is_err: boolean,
+    timestamp: string | null,
+    thread: string | null,
+    level: string | null,
+    logger: string | null,
+    message: string,
+    stack_trace: string[],
+}
+
+export type ConsoleBatch = {
+    records: ConsoleLine[]
 }
 
 type CCContext = {
-    channel: Channel<ConsoleLine> | undefined,
-    setChannel: (channel: Channel<ConsoleLine>) => void
+    channel: Channel<ConsoleBatch> | undefined,
+    setChannel: (channel: Channel<ConsoleBatch>) => void,
+    // The id of the process the channel belongs to
+    process: string | undefined,
+    setProcess: (process: string) => void
 };
 
 export const ConsoleChannel = React.createContext<CCContext>({
     channel: undefined,
-    setChannel: () => {}
+    setChannel: () => {},
+    process: undefined,
+    setProcess: () => {}
 })
 
 export const useConsole = () => useContext(ConsoleChannel)
@@ -62,7 +76,8 @@ interface TaskProgressData {
 export default function MyApp({Component, pageProps}: AppProps) {
     const [alertCount, setAlertCount] = useState(0);
     const [alerts, setAlerts] = useState<LauncherAlert[]>([]);
-    const [channel, setChannel] = useState<Channel<ConsoleLine> | undefined>(undefined)
+    const [channel, setChannel] = useState<Channel<ConsoleBatch> | undefined>(undefined)
+    const [process, setProcess] = useState<string | undefined>(undefined)
 
     const addAlert: AddAlert = (variant: Variant, content: ReactNode) => {
         let id = alertCount + 1;
@@ -137,7 +152,9 @@ export default function MyApp({Component, pageProps}: AppProps) {
         <ThemeProvider>
             <ConsoleChannel.Provider value={{
                 channel,
-                setChannel
+                setChannel,
+                process,
+                setProcess
             } as CCContext}>
                 <Alerts.Provider value={addAlert}>
                     <Component {...pageProps} />
@@ -4,10 +4,61 @@ import {useEffect, useRef, useState} from "react";
 import {invoke} from "@tauri-apps/api/core";
 import {useRouter} from "next/router";
 import {Alerts, ConsoleChannel, ConsoleLine, useConsole} from "@/pages/_app";
+import {listen} from "@tauri-apps/api/event";
 
+export type CrashSummary = {
+    source: "crash_report" | "jvm_fatal_error",
+    path: string,
+    description: string | null,
+    exception: string | null,
+    stack_trace: string[],
+    jvm: {
+        java_version: string | null,
+        vm: string | null,
+        flags: string | null,
+    },
+    suspects: string[],
+}
+
+export type Diagnosis = {
+    id: string,
+    title: string,
+    diagnosis: string,
+    fix: string,
+    evidence: string,
+}
+
+export type ProcessExit = {
+    id: string,
+    instance_id: string,
+    code: number | null,
+    signal: number | null,
+    duration_ms: number,
+    // Stopped from the launcher rather than exiting on its own
+    stopped: boolean,
+    crashes: CrashSummary[],
+    diagnoses: Diagnosis[],
+}
+
+
+// Back into the layout the game printed it in
+const formatLine = (line: ConsoleLine): string => {
+    let text = line.level != null
+        ? `[${line.timestamp}] [${line.thread}/${line.level}]${line.logger ? ` (${line.logger})` : ""}: ${line.message}`
+        : line.message
+
+    return [text, ...line.stack_trace].join("\n")
+}
+
+const lineColor = (line: ConsoleLine): string => {
+    if (line.level == "ERROR" || line.level == "FATAL" || (line.level == null && line.is_err)) return "red"
+    if (line.level == "WARN") return "orange"
+    return "inherit"
+}
 
 const Console: React.FC = () => {
     const [lines, setLines] = useState<ConsoleLine[]>([]);
+    const [exit, setExit] = useState<ProcessExit | null>(null);
     const router = useRouter();
     const consoleContentRef = useRef<HTMLDivElement>(null);
 
@@ -27,10 +78,20 @@ const Console: React.FC = () => {
 
     const console = useConsole()
 
-    if (console.channel) console.channel.onmessage = (message) => {
-        setLines((prev) => [...prev, message])
+    if (console.channel) console.channel.onmessage = (batch) => {
+        setLines((prev) => [...prev, ...batch.records])
     }
 
+    useEffect(() => {
+        const unlisten = listen<ProcessExit>("process-exit", (event) => {
+            if (event.payload.id == console.process) setExit(event.payload)
+        })
+
+        return () => {
+            unlisten.then(remove => remove())
+        }
+    }, [console.process])
+
     useEffect(() => {
         if (consoleContentRef.current) {
             consoleContentRef.current.scrollTop = consoleContentRef.current.scrollHeight;
@@ -42,19 +103,48 @@ const Console: React.FC = () => {
             <>
                 <div id={styles.consoleHeader}>
                     <h1>Game output</h1>
+                    {exit && <span>
+                        {exit.stopped
+                            ? "Stopped"
+                            : exit.signal != null ? `Exited from signal ${exit.signal}`
+                            : exit.code != null ? `Exited with code ${exit.code}`
+                            : "Exited with an unknown status"
+                        } after {Math.round(exit.duration_ms / 1000)}s
+                    </span>}
                 </div>
+                {exit && exit.crashes.map((crash, index) =>
+                    <Alert key={index} variant={"danger"}>
+                        <Alert.Heading>
+                            {crash.source == "crash_report" ? "The game crashed" : "The JVM crashed"}
+                            {crash.description && `: ${crash.description}`}
+                        </Alert.Heading>
+                        {crash.exception && <p>{crash.exception}</p>}
+                        {crash.suspects.length > 0 && <p>Possibly caused by {crash.suspects.join(", ")}</p>}
+                        <small>{crash.path}</small>
+                    </Alert>
+                )}
+                {exit && exit.diagnoses.map((diagnosis) =>
+                    <Alert key={diagnosis.id} variant={"warning"}>
+                        <Alert.Heading>{diagnosis.title}</Alert.Heading>
+                        <p>{diagnosis.diagnosis}</p>
+                        <p>{diagnosis.fix}</p>
+                        <small>{diagnosis.evidence}</small>
+                    </Alert>
+                )}
                 <div id={styles.consoleContent} ref={consoleContentRef}>
                     {lines.map((line, index) =>
                         <span key={index} className={styles.consoleLine} style={{
-                            color: line.is_err ? "red" : "inherit"
-                        }}>{mapLine(String.fromCharCode(...line.frag))}</span>
+                            color: lineColor(line)
+                        }}>{mapLine(formatLine(line) + "\n")}</span>
                     )}
                 </div>
                 <div id={styles.end_process}>
                     <Button
                         onClick={() => {
                             router.push("/home")
-                            invoke("end_launch_process").then(() => {
+                            invoke("end_launch_process", {
+                                id: console.process
+                            }).then(() => {
                             }).catch((it) => {
                                 addAlert(
                                     "danger",
@@ -72,9 +162,7 @@ const Console: React.FC = () => {
                 <div id={styles.export_logs}>
                     <Button
                         onClick={() => {
-                            let logs = lines.map((it) => {
-                                return String.fromCharCode(...it.frag)
-                            }).join("");
+                            let logs = lines.map(formatLine).join("\n");
                             invoke("export_logs", {
                                 "logs": logs
                             }).then(() => {
@@ -46,4 +46,33 @@ export type ExtensionPointer = {
     descriptor: string,
     repository: string,
     repository_type: string
-}
\ No newline at end of file
+}
+
+export type Instance = {
+    id: string,
+    name: string,
+    version: string,
+    game_dir: string,
+    extensions: ExtensionPointer[],
+    mods: {
+        project_id: string,
+        loader: string,
+    }[],
+    jvm: {
+        args: string[],
+        // Each replaces the global setting when set
+        min_memory: number | null,
+        max_memory: number | null,
+        preset: "none" | "g1" | "zgc" | "aikar" | null,
+        game_args: string[],
+    },
+    launch: {
+        // Put in front of the java command, like `nice -n 10`
+        wrapper: string | null,
+        // Shell commands run in the game directory, a failing pre-launch hook stops the launch
+        pre_launch: string | null,
+        post_exit: string | null,
+        env: { [name: string]: string },
+    },
+    icon: string | null,
+}